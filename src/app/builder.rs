@@ -0,0 +1,168 @@
+/// Synchronous builder for configuring an Expresso app before it is shared
+use crate::{
+    app::{expresso::Expresso, group::RouteGroup},
+    handler::{IntoHandler, IntoHandlers},
+    middleware::{for_prefix, MiddlewareManager},
+    router::{Method, Router},
+    server::config::ServerConfig,
+};
+
+/// Collects routes and middleware without locking or `.await`
+///
+/// Produces the same router and middleware structures as the async
+/// registration methods, which remain available on the built app.
+pub struct ExpressoBuilder {
+    router: Router,
+    middleware: MiddlewareManager,
+    config: ServerConfig,
+}
+
+impl ExpressoBuilder {
+    /// Create an empty builder
+    pub fn new() -> Self {
+        Self {
+            router: Router::new(),
+            middleware: MiddlewareManager::new(),
+            config: ServerConfig::default(),
+        }
+    }
+
+    /// Use the given server configuration
+    pub fn config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Register a global middleware
+    pub fn use_middleware<F>(mut self, f: F) -> Self
+    where
+        F: IntoHandler,
+    {
+        self.middleware.push(f.into_handler());
+        self
+    }
+
+    /// Register a global middleware under `name`, see `Expresso::describe_chain`
+    pub fn use_named_middleware<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: IntoHandler,
+    {
+        self.middleware.push_named(name, f.into_handler());
+        self
+    }
+
+    /// Register a middleware for paths at or below `prefix`, see `Expresso::use_middleware_for`
+    pub fn use_middleware_for<F>(mut self, prefix: &str, f: F) -> Self
+    where
+        F: IntoHandler,
+    {
+        self.middleware.push(for_prefix(prefix, f).into_handler());
+        self
+    }
+
+    /// Register a route for the given method
+    pub fn route<H>(mut self, method: Method, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.router
+            .insert_route(method, path, handlers.into_chained_handler());
+        self
+    }
+
+    /// Register a GET route
+    pub fn get<H>(self, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::GET, path, handlers)
+    }
+
+    /// Register a POST route
+    pub fn post<H>(self, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::POST, path, handlers)
+    }
+
+    /// Register a PUT route
+    pub fn put<H>(self, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::PUT, path, handlers)
+    }
+
+    /// Register a DELETE route
+    pub fn delete<H>(self, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::DELETE, path, handlers)
+    }
+
+    /// Register a PATCH route
+    pub fn patch<H>(self, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::PATCH, path, handlers)
+    }
+
+    /// Register a HEAD route
+    pub fn head<H>(self, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::HEAD, path, handlers)
+    }
+
+    /// Register an OPTIONS route
+    pub fn options<H>(self, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::OPTIONS, path, handlers)
+    }
+
+    /// Register a route for every method without a route of its own on `path`
+    pub fn any<H>(self, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::ANY, path, handlers)
+    }
+
+    /// Register a handler for every method on `path`; the same as `any`
+    pub fn all<H>(self, path: &str, handlers: H) -> Self
+    where
+        H: IntoHandlers,
+    {
+        self.any(path, handlers)
+    }
+
+    /// Register routes that share a path prefix and group-scoped middleware
+    pub fn group<F>(mut self, prefix: &str, f: F) -> Self
+    where
+        F: FnOnce(&mut RouteGroup),
+    {
+        let mut group = RouteGroup::new(prefix);
+        f(&mut group);
+        for (method, path, handler) in group.into_routes() {
+            self.router.insert_route(method, &path, handler);
+        }
+        self
+    }
+
+    /// Construct the app from the collected routes and middleware
+    pub fn build(self) -> Expresso {
+        Expresso::from_parts(self.router, self.middleware).with_config(self.config)
+    }
+}
+
+impl Default for ExpressoBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
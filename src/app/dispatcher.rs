@@ -0,0 +1,208 @@
+/// Request dispatch: routing, middleware, and app-level hooks
+use crate::{
+    errors::{panic_message, ErrorBodies, ErrorHook, PanicHook},
+    http::{request::Request, response::Response, timing::Timings},
+    middleware::MiddlewareManager,
+    router::{Method, Router},
+    server::config::ServerConfig,
+    types::{Handler, ResponseHook, RewriteHook},
+};
+use futures::FutureExt;
+use std::{panic::AssertUnwindSafe, sync::Arc, time::Duration};
+use tokio::{
+    sync::RwLock,
+    time::{timeout_at, Instant},
+};
+
+/// Application-level hooks consulted by the dispatcher
+#[derive(Default)]
+pub(crate) struct Hooks {
+    pub(crate) on_panic: Option<PanicHook>,
+    pub(crate) on_error: Option<ErrorHook>,
+    pub(crate) expose_panic_messages: bool,
+    pub(crate) rewrite: Option<RewriteHook>,
+    /// Answers requests whose path no route matches, in place of the 404
+    pub(crate) not_found: Option<Handler>,
+    /// Handed to the server at `listen`, which calls it after each write
+    pub(crate) on_response: Option<ResponseHook>,
+}
+
+/// Shared state needed to turn a parsed request into a response
+#[derive(Clone)]
+pub(crate) struct Dispatcher {
+    pub(crate) router: Arc<Router>,
+    pub(crate) middleware: Arc<MiddlewareManager>,
+    pub(crate) hooks: Arc<RwLock<Hooks>>,
+    pub(crate) error_bodies: ErrorBodies,
+    pub(crate) config: Arc<ServerConfig>,
+}
+
+impl Dispatcher {
+    /// Route a request through the middleware chain and apply the app hooks
+    pub(crate) async fn dispatch(self, mut req: Request) -> Response {
+        let error_bodies = self.error_bodies.for_accept(req.header("Accept"));
+
+        // TRACE echoes requests back (cross-site tracing), so refuse it outright
+        if req.method() == "TRACE" && !self.config.allow_trace {
+            return error_bodies.response(405, "Method Not Allowed").await;
+        }
+
+        // Rewritten paths are what middleware, routing and handlers all see
+        let rewrite = self.hooks.read().await.rewrite.clone();
+        if let Some(path) = rewrite.and_then(|rewrite| rewrite(req.path())) {
+            req.path = path;
+        }
+
+        // A deadline that has already passed never reaches the handlers
+        req.deadline = self.deadline(&req);
+        req.json_limits = self.config.json_limits;
+        if self.config.server_timing {
+            req.timings = Timings::enabled();
+        }
+        if req.remaining() == Some(Duration::ZERO) {
+            return error_bodies.response(503, "Service Unavailable").await;
+        }
+
+        let res = Response::new();
+
+        // Exact method, then the path's ANY route, then OPTIONS, 405 or 404
+        let route_handler: Handler = match self.router.find_route(req.method(), req.path()).await {
+            Some((handler, params)) => {
+                req.params = params;
+                handler
+            }
+            None => {
+                let mut allowed = self.router.methods_for_path(req.path()).await;
+                allowed.retain(|&method| method != Method::ANY);
+                if !allowed.is_empty() && !allowed.contains(&Method::OPTIONS) {
+                    // Answered below when no route handles it
+                    allowed.push(Method::OPTIONS);
+                }
+                let (code, text) = if allowed.is_empty() {
+                    if req.method() == "CONNECT" {
+                        // Tunnelling is not supported, so an unrouted CONNECT is unimplemented
+                        (501, "Not Implemented")
+                    } else {
+                        (404, "Not Found")
+                    }
+                } else if req.method() == "OPTIONS" {
+                    (204, "No Content")
+                } else {
+                    (405, "Method Not Allowed")
+                };
+                let allow = allowed
+                    .iter()
+                    .map(Method::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let error_bodies = error_bodies.clone();
+                let not_found = self.hooks.read().await.not_found.clone();
+                Arc::new(move |req, res, next| {
+                    let error_bodies = error_bodies.clone();
+                    let allow = allow.clone();
+                    let not_found = not_found.clone();
+                    Box::pin(async move {
+                        match code {
+                            204 => Response::new().status(204).set_header("Allow", &allow),
+                            405 => error_bodies
+                                .response(code, text)
+                                .await
+                                .set_header("Allow", &allow),
+                            _ => {
+                                let mut res = match not_found {
+                                    Some(handler) if code == 404 => handler(req, res, next).await,
+                                    _ => error_bodies.response(code, text).await,
+                                };
+                                // Still unmatched, so the SPA fallback can serve its index
+                                res.unmatched = true;
+                                res
+                            }
+                        }
+                    })
+                })
+            }
+        };
+
+        // Build middleware chain that wraps the route handler
+        let chain = self.middleware.build_chain(route_handler).await;
+        let request = req.clone();
+        let terminal = error_bodies.clone();
+        let deadline = req.deadline;
+        let run = AssertUnwindSafe(chain(
+            req,
+            res,
+            Arc::new(move || {
+                let terminal = terminal.clone();
+                Box::pin(async move { terminal.response(500, "Internal Error").await })
+            }),
+        ))
+        .catch_unwind();
+        let outcome = match deadline {
+            Some(deadline) => match timeout_at(deadline, run).await {
+                Ok(outcome) => outcome,
+                Err(_) => return error_bodies.response(503, "Service Unavailable").await,
+            },
+            None => run.await,
+        };
+
+        let hooks = self.hooks.read().await;
+        let res = match outcome {
+            Ok(res) => match (&res.error, &hooks.on_error) {
+                (Some(error), Some(hook)) => hook(&request, error),
+                (Some(error), None) => {
+                    eprintln!(
+                        "❌ [Error] {} {} - {}",
+                        request.method(),
+                        request.path(),
+                        error
+                    );
+                    if res.body.is_none() {
+                        let text = res.reason_text().to_string();
+                        res.send(&text)
+                    } else {
+                        res
+                    }
+                }
+                (None, _) => res,
+            },
+            Err(payload) => {
+                let message = panic_message(payload.as_ref());
+                match &hooks.on_panic {
+                    Some(hook) => hook(&request, message),
+                    None => match message {
+                        Some(message) if hooks.expose_panic_messages => {
+                            Response::new().status(500).send(&message)
+                        }
+                        _ => error_bodies.response(500, "Internal Server Error").await,
+                    },
+                }
+            }
+        };
+        with_server_timing(res, request.timings())
+    }
+
+    /// The sooner of the configured timeout and the client's `X-Request-Timeout`
+    fn deadline(&self, req: &Request) -> Option<Instant> {
+        let requested = req
+            .header("X-Request-Timeout")
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+        let timeout = match (self.config.request_timeout, requested) {
+            (Some(configured), Some(requested)) => Some(configured.min(requested)),
+            (configured, requested) => configured.or(requested),
+        };
+        timeout.and_then(|timeout| Instant::now().checked_add(timeout))
+    }
+}
+
+/// Add the recorded spans to any `Server-Timing` value the handler set
+fn with_server_timing(res: Response, timings: &Timings) -> Response {
+    let Some(value) = timings.header_value() else {
+        return res;
+    };
+    let value = match res.header("Server-Timing") {
+        Some(existing) => format!("{}, {}", existing, value),
+        None => value,
+    };
+    res.set_header("Server-Timing", &value)
+}
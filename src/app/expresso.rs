@@ -1,26 +1,75 @@
 /// Main Expresso application struct - simplified and modular
 use crate::{
+    app::{
+        builder::ExpressoBuilder,
+        dispatcher::{Dispatcher, Hooks},
+        group::RouteGroup,
+    },
+    errors::{ErrorBodies, HandlerError},
     handler::{IntoHandler, IntoHandlers},
     http::{request::Request, response::Response},
-    middleware::MiddlewareManager,
+    middleware::{for_prefix, MiddlewareManager},
+    middlewares::maintenance::MaintenanceFlag,
     router::{Method, Router},
-    server::listener::Server,
+    server::{
+        config::ServerConfig,
+        listener::{Listener, Server},
+    },
 };
-use std::{net::SocketAddr, sync::Arc};
+use std::{future::Future, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{sync::RwLock, task::JoinHandle};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 pub struct Expresso {
     router: Arc<Router>,
     middleware: Arc<MiddlewareManager>,
+    hooks: Arc<RwLock<Hooks>>,
+    error_bodies: ErrorBodies,
+    config: ServerConfig,
+    shutdown: CancellationToken,
+    maintenance: MaintenanceFlag,
+    tasks: TaskTracker,
 }
 
 impl Expresso {
     pub fn new() -> Self {
+        Self::from_parts(Router::new(), MiddlewareManager::new())
+    }
+
+    /// Start a synchronous builder that registers routes and middleware up front
+    pub fn builder() -> ExpressoBuilder {
+        ExpressoBuilder::new()
+    }
+
+    pub(crate) fn from_parts(router: Router, middleware: MiddlewareManager) -> Self {
         Self {
-            router: Arc::new(Router::new()),
-            middleware: Arc::new(MiddlewareManager::new()),
+            router: Arc::new(router),
+            middleware: Arc::new(middleware),
+            hooks: Arc::new(RwLock::new(Hooks::default())),
+            error_bodies: ErrorBodies::default(),
+            config: ServerConfig::default(),
+            shutdown: CancellationToken::new(),
+            maintenance: MaintenanceFlag::new(),
+            tasks: TaskTracker::new(),
         }
     }
 
+    /// Use the given server configuration for subsequent `listen` calls
+    pub fn with_config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Register a route for the given method
+    pub async fn route<H>(&self, method: Method, path: &str, handlers: H)
+    where
+        H: IntoHandlers,
+    {
+        self.router
+            .add_route(method, path, handlers.into_chained_handler())
+            .await;
+    }
+
     pub async fn use_middleware<F>(&self, f: F)
     where
         F: IntoHandler,
@@ -28,6 +77,50 @@ impl Expresso {
         self.middleware.add(f.into_handler()).await;
     }
 
+    /// Register a global middleware under `name`, as listed by `describe_chain`
+    pub async fn use_named_middleware<F>(&self, name: &str, f: F)
+    where
+        F: IntoHandler,
+    {
+        self.middleware.add_named(name, f.into_handler()).await;
+    }
+
+    /// Register a middleware that only runs for paths at or below `prefix`
+    ///
+    /// It keeps its place in the global order, so middleware registered
+    /// before it still runs first. See `middleware::for_prefix`.
+    ///
+    /// ```ignore
+    /// app.use_middleware(logger).await;
+    /// app.use_middleware_for("/admin", auth).await;
+    /// ```
+    pub async fn use_middleware_for<F>(&self, prefix: &str, f: F)
+    where
+        F: IntoHandler,
+    {
+        self.middleware
+            .add(for_prefix(prefix, f).into_handler())
+            .await;
+    }
+
+    /// Number of registered global middlewares
+    pub async fn middleware_count(&self) -> usize {
+        self.middleware.count().await
+    }
+
+    /// Names of the global middlewares in the order they run
+    ///
+    /// Middleware registered with `use_middleware` is listed as
+    /// `"<anonymous>"`. Group and route middleware are not included.
+    ///
+    /// ```ignore
+    /// app.use_named_middleware("auth", auth).await;
+    /// println!("{}", app.describe_chain().await.join(" → ")); // auth
+    /// ```
+    pub async fn describe_chain(&self) -> Vec<String> {
+        self.middleware.names().await
+    }
+
     pub async fn get<H>(&self, path: &str, handlers: H)
     where
         H: IntoHandlers,
@@ -77,56 +170,467 @@ impl Expresso {
             .await;
     }
 
+    /// Register a HEAD route
+    pub async fn head<H>(&self, path: &str, handlers: H)
+    where
+        H: IntoHandlers,
+    {
+        self.router
+            .add_route(Method::HEAD, path, handlers.into_chained_handler())
+            .await;
+    }
+
+    /// Register an OPTIONS route, replacing the automatic `Allow` answer for `path`
+    pub async fn options<H>(&self, path: &str, handlers: H)
+    where
+        H: IntoHandlers,
+    {
+        self.router
+            .add_route(Method::OPTIONS, path, handlers.into_chained_handler())
+            .await;
+    }
+
+    /// Register a route for every method without a route of its own on `path`
+    ///
+    /// Lookup tries the exact method first, then `any`, then answers 405
+    /// with an `Allow` header if the path has other routes, else 404.
+    ///
+    /// ```ignore
+    /// app.get("/items", (list_items,)).await;
+    /// app.any("/items", (|_req, res: Response, _next| async move {
+    ///     res.status(405).send("read-only")
+    /// },))
+    /// .await;
+    /// ```
+    pub async fn any<H>(&self, path: &str, handlers: H)
+    where
+        H: IntoHandlers,
+    {
+        self.router
+            .add_route(Method::ANY, path, handlers.into_chained_handler())
+            .await;
+    }
+
+    /// Register a handler for every method on `path`, as Express's `app.all`
+    ///
+    /// Same as `any`: routes registered for a specific method on the same
+    /// path still take precedence, whichever is registered first. This
+    /// includes `OPTIONS`, so the handler also answers CORS preflight
+    /// requests unless the path has an `options` route of its own.
+    pub async fn all<H>(&self, path: &str, handlers: H)
+    where
+        H: IntoHandlers,
+    {
+        self.any(path, handlers).await;
+    }
+
+    /// Register routes that share a path prefix and group-scoped middleware
+    ///
+    /// ```ignore
+    /// app.group("/api/v1", |g| {
+    ///     g.use_middleware(auth);
+    ///     g.get("/users", (list_users,));
+    /// })
+    /// .await;
+    /// ```
+    pub async fn group<F>(&self, prefix: &str, f: F)
+    where
+        F: FnOnce(&mut RouteGroup),
+    {
+        let mut group = RouteGroup::new(prefix);
+        f(&mut group);
+        for (method, path, handler) in group.into_routes() {
+            self.router.add_route(method, &path, handler).await;
+        }
+    }
+
+    /// Listen on `127.0.0.1` at the given port
     pub async fn listen<F>(&self, port: u16, callback: F) -> tokio::io::Result<()>
     where
         F: FnOnce() + Send + 'static,
     {
-        let addr: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
-        let server = Server::new(addr);
-        let router = Arc::clone(&self.router);
-        let middleware = Arc::clone(&self.middleware);
+        self.serve(SocketAddr::from(([127, 0, 0, 1], port)), |_| callback())
+            .await
+    }
 
-        callback();
+    /// Listen on an explicit address such as `0.0.0.0:3000`, `[::1]:3000` or `[::]:3000`
+    /// IPv6 literals must be bracketed; see `ServerConfig::dual_stack` for IPv4 on `[::]`
+    pub async fn listen_on<F>(&self, addr: &str, callback: F) -> tokio::io::Result<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.serve(parse_addr(addr)?, |_| callback()).await
+    }
 
-        server
-            .listen(move |req: Request| {
-                let router = router.clone();
-                let middleware = middleware.clone();
-
-                async move {
-                    let res = Response::new();
-                    let method = req.method();
-                    let path = req.path();
-
-                    // Find the route handler or use 404 handler
-                    let route_handler =
-                        router.find_handler(method, path).await.unwrap_or_else(|| {
-                            Arc::new(|_req, res, _next| {
-                                Box::pin(async move { res.status(404).send("Not Found") })
-                            })
-                        });
-
-                    // Build middleware chain that wraps the route handler
-                    let chain = middleware.build_chain(route_handler).await;
-                    chain(
-                        req,
-                        res,
-                        Arc::new(|| {
-                            Box::pin(async { Response::new().status(500).send("Internal Error") })
-                        }),
-                    )
-                    .await
+    /// Listen on an explicit address, passing the bound address to `callback`
+    ///
+    /// Useful with port 0, where the OS picks a free port:
+    ///
+    /// ```ignore
+    /// app.listen_with_addr("127.0.0.1:0", |addr| println!("listening on {}", addr))
+    ///     .await?;
+    /// ```
+    pub async fn listen_with_addr<F>(&self, addr: &str, callback: F) -> tokio::io::Result<()>
+    where
+        F: FnOnce(SocketAddr) + Send + 'static,
+    {
+        self.serve(parse_addr(addr)?, callback).await
+    }
+
+    /// Listen on an explicit address until `signal` completes, then shut down
+    ///
+    /// When `signal` resolves the app shuts down as with `shutdown`: no new
+    /// connections are accepted, in-flight requests and background tasks get
+    /// up to `ServerConfig::shutdown_grace` to finish, and this returns
+    /// `Ok(())`. Calling `shutdown` stops it as well.
+    ///
+    /// ```ignore
+    /// app.listen_with_shutdown(
+    ///     "0.0.0.0:3000",
+    ///     async { tokio::signal::ctrl_c().await.ok(); },
+    ///     |addr| println!("listening on {}", addr),
+    /// )
+    /// .await?;
+    /// ```
+    pub async fn listen_with_shutdown<S, F>(
+        &self,
+        addr: &str,
+        signal: S,
+        callback: F,
+    ) -> tokio::io::Result<()>
+    where
+        S: Future<Output = ()> + Send + 'static,
+        F: FnOnce(SocketAddr) + Send + 'static,
+    {
+        let addr = parse_addr(addr)?;
+        let shutdown = self.shutdown.clone();
+        let watcher = tokio::spawn(async move {
+            tokio::select! {
+                _ = signal => shutdown.cancel(),
+                _ = shutdown.cancelled() => {}
+            }
+        });
+        let result = self.serve(addr, callback).await;
+        watcher.abort();
+        result
+    }
+
+    /// Listen on several addresses at once, such as HTTP on :80 and HTTPS on :443
+    ///
+    /// Every listener is bound before any is served, and `callback` receives
+    /// the bound addresses in order. If one fails to bind, the ones already
+    /// bound are closed and the error is returned. All listeners share the
+    /// routes, middleware and shutdown signal; if one stops with an error,
+    /// the others shut down too and that error is returned once all are done.
+    ///
+    /// ```ignore
+    /// let tls = tls::from_pem_files("fullchain.pem", "key.pem")?;
+    /// app.use_middleware(https::require_https).await;
+    /// app.listen_multi(
+    ///     &[
+    ///         Listener::Http("0.0.0.0:80".parse()?),
+    ///         Listener::Https("0.0.0.0:443".parse()?, tls),
+    ///     ],
+    ///     |addrs| println!("listening on {:?}", addrs),
+    /// )
+    /// .await?;
+    /// ```
+    pub async fn listen_multi<F>(
+        &self,
+        listeners: &[Listener],
+        callback: F,
+    ) -> tokio::io::Result<()>
+    where
+        F: FnOnce(Vec<SocketAddr>) + Send + 'static,
+    {
+        // A failing listener stops its siblings without shutting the app down
+        let stop = self.shutdown.child_token();
+        let mut bound = Vec::with_capacity(listeners.len());
+        for listener in listeners {
+            let server = self.server(listener.addr(), stop.clone()).await;
+            #[cfg(feature = "tls")]
+            let server = match listener {
+                Listener::Https(_, tls) => server.with_tls(tls.clone()),
+                Listener::Http(_) => server,
+            };
+            let socket = server.bind().await?;
+            bound.push((server, socket));
+        }
+        callback(
+            bound
+                .iter()
+                .map(|(_, socket)| socket.local_addr())
+                .collect::<tokio::io::Result<_>>()?,
+        );
+
+        let dispatcher = self.dispatcher();
+        let results = futures::future::join_all(bound.into_iter().map(|(server, socket)| {
+            let dispatcher = dispatcher.clone();
+            let stop = stop.clone();
+            async move {
+                let result = server
+                    .serve(socket, move |req: Request| dispatcher.clone().dispatch(req))
+                    .await;
+                if result.is_err() {
+                    stop.cancel();
                 }
+                result
+            }
+        }))
+        .await;
+        results.into_iter().collect()
+    }
+
+    async fn serve<F>(&self, addr: SocketAddr, callback: F) -> tokio::io::Result<()>
+    where
+        F: FnOnce(SocketAddr) + Send + 'static,
+    {
+        let server = self.server(addr, self.shutdown.clone()).await;
+        let dispatcher = self.dispatcher();
+
+        let listener = server.bind().await?;
+        callback(listener.local_addr()?);
+
+        server
+            .serve(listener, move |req: Request| {
+                dispatcher.clone().dispatch(req)
             })
             .await
     }
 
+    /// A server for `addr` with the app's configuration, stopped by `shutdown`
+    async fn server(&self, addr: SocketAddr, shutdown: CancellationToken) -> Server {
+        let server = Server::new(addr)
+            .with_config(self.config.clone())
+            .with_error_bodies(self.error_bodies.clone())
+            .with_shutdown(shutdown)
+            .with_tasks(self.tasks.clone());
+        match self.hooks.read().await.on_response.clone() {
+            Some(hook) => server.with_response_hook(hook),
+            None => server,
+        }
+    }
+
+    fn dispatcher(&self) -> Dispatcher {
+        Dispatcher {
+            router: Arc::clone(&self.router),
+            middleware: Arc::clone(&self.middleware),
+            hooks: Arc::clone(&self.hooks),
+            error_bodies: self.error_bodies.clone(),
+            config: Arc::new(self.config.clone()),
+        }
+    }
+
+    /// Replace the body of framework-generated error responses for `code`
+    ///
+    /// Used for the 404 fallback, the dispatcher's terminal 500 and default
+    /// panic response, the 405 for `TRACE`, the 501 for unrouted `CONNECT`,
+    /// and the 400 sent for unparseable requests.
+    ///
+    /// ```ignore
+    /// app.set_default_error_body(404, r#"{"error":"not found"}"#, "application/json")
+    ///     .await;
+    /// ```
+    pub async fn set_default_error_body(&self, code: u16, body: &str, content_type: &str) {
+        self.error_bodies.set(code, body, content_type).await;
+    }
+
+    /// Format framework-generated errors for clients whose `Accept` prefers `media_type`
+    ///
+    /// Plain text and `application/json` are built in; registering one of
+    /// them again replaces it, and a body set with `set_default_error_body`
+    /// still wins for its code. `formatter` receives the status and message.
+    ///
+    /// ```ignore
+    /// app.error_format("text/html", |code, message| {
+    ///     Response::new().status(code).html(&format!("<h1>{}</h1>", message))
+    /// })
+    /// .await;
+    /// ```
+    pub async fn error_format<F>(&self, media_type: &str, formatter: F)
+    where
+        F: Fn(u16, &str) -> Response + Send + Sync + 'static,
+    {
+        self.error_bodies
+            .set_format(media_type, Arc::new(formatter))
+            .await;
+    }
+
+    /// Build the response sent when a handler panics
+    /// Receives the panic message when the payload is a string
+    pub async fn on_panic<F>(&self, hook: F)
+    where
+        F: Fn(&Request, Option<String>) -> Response + Send + Sync + 'static,
+    {
+        self.hooks.write().await.on_panic = Some(Arc::new(hook));
+    }
+
+    /// Build the response sent when a handler fails via `Response::fail`
+    pub async fn on_error<F>(&self, hook: F)
+    where
+        F: Fn(&Request, &HandlerError) -> Response + Send + Sync + 'static,
+    {
+        self.hooks.write().await.on_error = Some(Arc::new(hook));
+    }
+
+    /// Same as `on_error`: build the 500 sent when a handler fails
+    ///
+    /// Pairs with `not_found` for the two app-wide error pages. Panics are
+    /// handled separately by `on_panic`, and framework-generated errors
+    /// (400, 405, ...) by `error_format`.
+    ///
+    /// ```ignore
+    /// app.error_handler(|_req, err| {
+    ///     Response::new()
+    ///         .status(500)
+    ///         .json_object(&[("error", err.to_string().into())])
+    /// })
+    /// .await;
+    /// ```
+    pub async fn error_handler<F>(&self, handler: F)
+    where
+        F: Fn(&Request, &HandlerError) -> Response + Send + Sync + 'static,
+    {
+        self.on_error(handler).await;
+    }
+
+    /// Answer requests whose path no route matches, in place of the 404
+    ///
+    /// Runs behind the app's middleware like any route. Paths that have
+    /// routes for other methods still get 405, and `error_format` and
+    /// `on_error` shape the remaining error responses.
+    ///
+    /// ```ignore
+    /// app.not_found((|req: Request, res: Response, _next: Next| async move {
+    ///     res.status(404).json_object(&[("missing", req.path().into())])
+    /// },))
+    /// .await;
+    /// ```
+    pub async fn not_found<H>(&self, handlers: H)
+    where
+        H: IntoHandlers,
+    {
+        self.hooks.write().await.not_found = Some(handlers.into_chained_handler());
+    }
+
+    /// Rewrite request paths before routing, e.g. to serve legacy URLs
+    ///
+    /// Runs once per request, before middleware and route lookup, on the
+    /// path without its query string. Returning `None` leaves it unchanged.
+    ///
+    /// The hook sees the path exactly as the client sent it, which is also
+    /// what routes are matched against: it is not percent-decoded and `.` or
+    /// `..` segments are not resolved, so `/v1%2Fhello` and `/v1/./hello` are
+    /// not `/v1/hello`. Decode with `http::url::percent_decode` in the hook if
+    /// a rule must see through escapes.
+    ///
+    /// ```ignore
+    /// app.rewrite(|path| path.strip_prefix("/v1").map(str::to_string)).await;
+    /// ```
+    pub async fn rewrite<F>(&self, hook: F)
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.hooks.write().await.rewrite = Some(Arc::new(hook));
+    }
+
+    /// Observe every response after it has been written
+    ///
+    /// Receives the request, the response, the time from reading the request
+    /// to the end of the write, and the bytes written, head included. Unlike
+    /// middleware it also sees responses the server generates itself (400,
+    /// 413, 431, ...), where the request is empty if it couldn't be parsed.
+    /// When the client goes away mid-write the partial byte count is
+    /// reported; a handler cancelled before responding reports nothing.
+    /// Register it before `listen`.
+    ///
+    /// ```ignore
+    /// app.on_response(|req, res, elapsed, bytes| {
+    ///     metrics.record(req.path(), res.status_code, elapsed, bytes);
+    /// })
+    /// .await;
+    /// ```
+    pub async fn on_response<F>(&self, hook: F)
+    where
+        F: Fn(&Request, &Response, Duration, u64) + Send + Sync + 'static,
+    {
+        self.hooks.write().await.on_response = Some(Arc::new(hook));
+    }
+
+    /// Include panic messages in the default 500 body (off by default)
+    /// Only affects panics when no `on_panic` hook is registered
+    pub async fn expose_panic_messages(&self, expose: bool) {
+        self.hooks.write().await.expose_panic_messages = expose;
+    }
+
+    /// Token cancelled when the app begins shutting down
+    /// Handlers see the same token through `Request::shutdown_signal`
+    pub fn shutdown_signal(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Run a background task that is part of the app's lifecycle
+    ///
+    /// `task` receives the shutdown token and should return soon after it is
+    /// cancelled. Once shutdown begins, `listen` waits for these tasks along
+    /// with in-flight requests, up to `ServerConfig::shutdown_grace`.
+    ///
+    /// ```ignore
+    /// app.spawn(|shutdown| async move {
+    ///     let mut interval = tokio::time::interval(Duration::from_secs(60));
+    ///     loop {
+    ///         tokio::select! {
+    ///             _ = shutdown.cancelled() => break,
+    ///             _ = interval.tick() => refresh_cache().await,
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn spawn<F, Fut>(&self, task: F) -> JoinHandle<Fut::Output>
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        self.tasks.spawn(task(self.shutdown.clone()))
+    }
+
+    /// Stop accepting connections and signal running handlers to finish
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    /// Turn maintenance mode on or off, effective for the next request
+    /// Only observed by the `maintenance` middleware built from `maintenance_flag`
+    pub fn set_maintenance(&self, enabled: bool) {
+        self.maintenance.set(enabled);
+    }
+
+    /// Flag toggled by `set_maintenance`, for the maintenance middleware
+    ///
+    /// ```ignore
+    /// app.use_middleware(maintenance::maintenance(app.maintenance_flag())).await;
+    /// app.set_maintenance(true); // 503 + Retry-After, except /health
+    /// ```
+    pub fn maintenance_flag(&self) -> MaintenanceFlag {
+        self.maintenance.clone()
+    }
+
     /// Get all registered routes (useful for debugging)
     pub async fn routes(&self) -> Vec<String> {
         self.router.get_all_routes().await
     }
 }
 
+fn parse_addr(addr: &str) -> tokio::io::Result<SocketAddr> {
+    addr.parse().map_err(|_| {
+        tokio::io::Error::new(
+            tokio::io::ErrorKind::InvalidInput,
+            format!("invalid socket address: {}", addr),
+        )
+    })
+}
+
 impl Default for Expresso {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,189 @@
+/// Route groups sharing a path prefix and middleware
+use crate::{
+    errors::{ErrorHook, HandlerError},
+    handler::{chain, IntoHandler, IntoHandlers},
+    http::{request::Request, response::Response},
+    middleware::default_content_type,
+    router::Method,
+    types::Handler,
+};
+use std::sync::Arc;
+
+/// Scoped registrations collected by `Expresso::group`
+///
+/// Every route path is prefixed with the group prefix, and the group's
+/// middleware runs only for the group's routes. At request time global
+/// middleware runs first, then group middleware in registration order,
+/// then the route's own handlers. Group middleware applies to every route
+/// in the group regardless of where it was registered inside the closure.
+///
+/// Errors from `Response::fail` propagate outwards: the route's handlers,
+/// the group middleware, the group's `on_error` boundary, global
+/// middleware, the app's `on_error`, and finally the default error logging.
+pub struct RouteGroup {
+    prefix: String,
+    middlewares: Vec<Handler>,
+    routes: Vec<(Method, String, Handler)>,
+    on_error: Option<ErrorHook>,
+}
+
+impl RouteGroup {
+    pub(crate) fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            middlewares: Vec::new(),
+            routes: Vec::new(),
+            on_error: None,
+        }
+    }
+
+    /// Register a middleware that only runs for routes in this group
+    pub fn use_middleware<F>(&mut self, f: F) -> &mut Self
+    where
+        F: IntoHandler,
+    {
+        self.middlewares.push(f.into_handler());
+        self
+    }
+
+    /// Set `Content-Type` for the group's responses that have a body but no type
+    /// Wraps all other group middleware, so it also covers bodies they set
+    pub fn default_content_type(&mut self, content_type: &'static str) -> &mut Self {
+        self.middlewares
+            .insert(0, default_content_type(content_type).into_handler());
+        self
+    }
+
+    /// Build the response for errors from this group's routes
+    ///
+    /// Catches errors from the routes and the group middleware before global
+    /// middleware sees them, so the app's `on_error` only handles errors the
+    /// group doesn't. A hook that returns a failed response passes that error
+    /// on to the app's hook.
+    ///
+    /// ```ignore
+    /// app.group("/api", |api| {
+    ///     api.on_error(|_req, err| Response::new().status(500).json_object(&[
+    ///         ("error", err.to_string().into()),
+    ///     ]));
+    /// })
+    /// .await;
+    /// ```
+    pub fn on_error<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn(&Request, &HandlerError) -> Response + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(hook));
+        self
+    }
+
+    /// Register a route for the given method under the group prefix
+    pub fn route<H>(&mut self, method: Method, path: &str, handlers: H) -> &mut Self
+    where
+        H: IntoHandlers,
+    {
+        let path = match path.trim_start_matches('/') {
+            "" if self.prefix.is_empty() => "/".to_string(),
+            "" => self.prefix.clone(),
+            rest => format!("{}/{}", self.prefix, rest),
+        };
+        self.routes
+            .push((method, path, handlers.into_chained_handler()));
+        self
+    }
+
+    /// Register a GET route
+    pub fn get<H>(&mut self, path: &str, handlers: H) -> &mut Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::GET, path, handlers)
+    }
+
+    /// Register a POST route
+    pub fn post<H>(&mut self, path: &str, handlers: H) -> &mut Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::POST, path, handlers)
+    }
+
+    /// Register a PUT route
+    pub fn put<H>(&mut self, path: &str, handlers: H) -> &mut Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::PUT, path, handlers)
+    }
+
+    /// Register a DELETE route
+    pub fn delete<H>(&mut self, path: &str, handlers: H) -> &mut Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::DELETE, path, handlers)
+    }
+
+    /// Register a PATCH route
+    pub fn patch<H>(&mut self, path: &str, handlers: H) -> &mut Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::PATCH, path, handlers)
+    }
+
+    /// Register a route for every method without a route of its own on `path`
+    pub fn any<H>(&mut self, path: &str, handlers: H) -> &mut Self
+    where
+        H: IntoHandlers,
+    {
+        self.route(Method::ANY, path, handlers)
+    }
+
+    /// Register a handler for every method on `path`; the same as `any`
+    pub fn all<H>(&mut self, path: &str, handlers: H) -> &mut Self
+    where
+        H: IntoHandlers,
+    {
+        self.any(path, handlers)
+    }
+
+    /// Resolve the collected routes, wrapping each with the group middleware
+    /// and error boundary
+    pub(crate) fn into_routes(self) -> Vec<(Method, String, Handler)> {
+        let middlewares = self.middlewares;
+        let on_error = self.on_error;
+        self.routes
+            .into_iter()
+            .map(|(method, path, handler)| {
+                let handler = if middlewares.is_empty() {
+                    handler
+                } else {
+                    let mut handlers = middlewares.clone();
+                    handlers.push(handler);
+                    chain(handlers)
+                };
+                match &on_error {
+                    Some(hook) => (method, path, error_boundary(handler, hook.clone())),
+                    None => (method, path, handler),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Run `handler`, replacing a failed response with the one `hook` builds
+fn error_boundary(handler: Handler, hook: ErrorHook) -> Handler {
+    Arc::new(move |req, res, next| {
+        let handler = handler.clone();
+        let hook = hook.clone();
+        Box::pin(async move {
+            let request = req.clone();
+            let res = handler(req, res, next).await;
+            match &res.error {
+                Some(error) => hook(&request, error),
+                None => res,
+            }
+        })
+    })
+}
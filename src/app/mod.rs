@@ -1 +1,4 @@
+pub mod builder;
+mod dispatcher;
 pub mod expresso;
+pub mod group;
@@ -1 +1,145 @@
-// Error types will go here
+/// Error types shared by handlers and the dispatcher
+use crate::http::{qlist, request::Request, response::Response};
+use std::{any::Any, collections::HashMap, error::Error, fmt, sync::Arc};
+use tokio::sync::RwLock;
+
+/// Boxed error accepted wherever a handler reports a failure
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+/// Error attached to a response by a handler via `Response::fail`
+#[derive(Clone)]
+pub struct HandlerError(Arc<dyn Error + Send + Sync>);
+
+impl HandlerError {
+    pub fn new<E>(error: E) -> Self
+    where
+        E: Into<BoxError>,
+    {
+        Self(Arc::from(error.into()))
+    }
+
+    /// Borrow the underlying error value
+    pub fn inner(&self) -> &(dyn Error + Send + Sync + 'static) {
+        self.0.as_ref()
+    }
+}
+
+impl fmt::Display for HandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for HandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+/// Builds the response for a request whose handler panicked
+/// Receives the panic message when the payload is a string
+pub type PanicHook = Arc<dyn Fn(&Request, Option<String>) -> Response + Send + Sync>;
+
+/// Builds the response for a request whose handler returned an error
+pub type ErrorHook = Arc<dyn Fn(&Request, &HandlerError) -> Response + Send + Sync>;
+
+/// Extract the message from a panic payload when it is a string
+pub fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+}
+
+/// Builds a framework-generated error response from its status and message
+pub type ErrorFormatter = Arc<dyn Fn(u16, &str) -> Response + Send + Sync>;
+
+/// Registry of bodies for framework-generated error responses (404, 500, 400)
+///
+/// A body registered for a code always wins. Otherwise the response is built
+/// by the format the request's `Accept` header prefers: plain text by
+/// default, or `{"error": "...", "status": 404}` for `application/json`.
+/// Without `Accept`, or when no format is acceptable, the first format is used.
+#[derive(Clone)]
+pub struct ErrorBodies {
+    bodies: Arc<RwLock<HashMap<u16, (String, String)>>>,
+    formats: Arc<RwLock<Vec<(String, ErrorFormatter)>>>,
+    /// `Accept` of the request being answered, when known
+    accept: Option<Arc<str>>,
+}
+
+impl Default for ErrorBodies {
+    fn default() -> Self {
+        let text: ErrorFormatter =
+            Arc::new(|code, message| Response::new().status(code).send(message));
+        let json: ErrorFormatter = Arc::new(|code, message| {
+            Response::new()
+                .status(code)
+                .json_object(&[("error", message.into()), ("status", code.into())])
+        });
+        Self {
+            bodies: Arc::default(),
+            formats: Arc::new(RwLock::new(vec![
+                ("text/plain".to_string(), text),
+                ("application/json".to_string(), json),
+            ])),
+            accept: None,
+        }
+    }
+}
+
+impl ErrorBodies {
+    /// Use `body` with the given content type for responses with `code`
+    pub async fn set(&self, code: u16, body: &str, content_type: &str) {
+        self.bodies
+            .write()
+            .await
+            .insert(code, (body.to_string(), content_type.to_string()));
+    }
+
+    /// Build error responses with `formatter` for clients preferring `media_type`
+    /// Replaces the format already registered for that type, if any
+    pub async fn set_format(&self, media_type: &str, formatter: ErrorFormatter) {
+        let mut formats = self.formats.write().await;
+        match formats
+            .iter_mut()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(media_type))
+        {
+            Some((_, existing)) => *existing = formatter,
+            None => formats.push((media_type.to_string(), formatter)),
+        }
+    }
+
+    /// A handle sharing the registry that negotiates against `accept`
+    pub(crate) fn for_accept(&self, accept: Option<&String>) -> Self {
+        Self {
+            accept: accept.map(|accept| Arc::from(accept.as_str())),
+            ..self.clone()
+        }
+    }
+
+    /// Build the response for `code`, using `fallback` when nothing is registered
+    pub async fn response(&self, code: u16, fallback: &str) -> Response {
+        if let Some((body, content_type)) = self.bodies.read().await.get(&code) {
+            return Response::new()
+                .status(code)
+                .content_type(content_type)
+                .send(body);
+        }
+        let formats = self.formats.read().await;
+        let media_types: Vec<&str> = formats
+            .iter()
+            .map(|(media_type, _)| media_type.as_str())
+            .collect();
+        let chosen = self
+            .accept
+            .as_deref()
+            .and_then(|accept| qlist::preferred_media_type(accept, &media_types))
+            .and_then(|chosen| formats.iter().find(|(media_type, _)| media_type == chosen))
+            .or(formats.first());
+        match chosen {
+            Some((_, formatter)) => formatter(code, fallback),
+            None => Response::new().status(code).send(fallback),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+/// Typed handler arguments (requires the `serde` feature)
+///
+/// Handlers wrapped with `extract` take arguments implementing `FromRequest`
+/// instead of `(Request, Response, Next)`. Extraction runs in argument order,
+/// and the first failure is returned to the client as a 4xx response.
+///
+/// ```ignore
+/// #[derive(Deserialize)]
+/// struct Page { page: u32 }
+///
+/// app.get("/users", (extract(|Query(p): Query<Page>| async move {
+///     Response::new().status(200).send(&format!("page {}", p.page))
+/// }),))
+/// .await;
+/// ```
+use crate::{
+    http::{
+        request::Request,
+        response::{IntoResponse, Response},
+    },
+    types::{BoxFuture, Next},
+};
+use serde::de::DeserializeOwned;
+use std::{future::Future, sync::Arc};
+
+/// A value built from the incoming request
+pub trait FromRequest: Sized {
+    /// Build the value, or return the response sent instead
+    #[allow(clippy::result_large_err)]
+    fn from_request(req: &Request) -> Result<Self, Response>;
+}
+
+/// The whole request, for handlers that also need raw access
+impl FromRequest for Request {
+    fn from_request(req: &Request) -> Result<Self, Response> {
+        Ok(req.clone())
+    }
+}
+
+/// Path parameters deserialized into `T`; 400 when they don't fit
+///
+/// Reads `Request::params`, which the router fills for patterned routes.
+pub struct Path<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for Path<T> {
+    fn from_request(req: &Request) -> Result<Self, Response> {
+        let encoded = serde_urlencoded::to_string(req.params()).unwrap_or_default();
+        serde_urlencoded::from_str(&encoded)
+            .map(Path)
+            .map_err(|err| bad_request("path parameters", err))
+    }
+}
+
+/// The query string deserialized into `T`; 400 when it doesn't fit
+pub struct Query<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for Query<T> {
+    fn from_request(req: &Request) -> Result<Self, Response> {
+        serde_urlencoded::from_str(req.query_string.as_deref().unwrap_or_default())
+            .map(Query)
+            .map_err(|err| bad_request("query string", err))
+    }
+}
+
+/// The JSON body deserialized into `T`; failures respond like `JsonError`
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for Json<T> {
+    fn from_request(req: &Request) -> Result<Self, Response> {
+        req.json().map(Json).map_err(IntoResponse::into_response)
+    }
+}
+
+fn bad_request(what: &str, err: serde_urlencoded::de::Error) -> Response {
+    Response::new()
+        .status(400)
+        .send(&format!("Bad Request: invalid {}: {}", what, err))
+}
+
+/// A function whose arguments can all be extracted from the request
+/// Implemented for async functions of one to four `FromRequest` arguments
+pub trait ExtractHandler<Args>: Send + Sync + 'static {
+    fn call(&self, req: &Request) -> BoxFuture;
+}
+
+macro_rules! impl_extract_handler {
+    ($($arg:ident),+) => {
+        impl<F, Fut, R, $($arg),+> ExtractHandler<($($arg,)+)> for F
+        where
+            F: Fn($($arg),+) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = R> + Send + 'static,
+            R: IntoResponse,
+            $($arg: FromRequest,)+
+        {
+            #[allow(non_snake_case)]
+            fn call(&self, req: &Request) -> BoxFuture {
+                $(
+                    let $arg = match $arg::from_request(req) {
+                        Ok(value) => value,
+                        Err(rejection) => return Box::pin(async move { rejection }),
+                    };
+                )+
+                let fut = self($($arg),+);
+                Box::pin(async move { fut.await.into_response() })
+            }
+        }
+    };
+}
+
+impl_extract_handler!(A);
+impl_extract_handler!(A, B);
+impl_extract_handler!(A, B, C);
+impl_extract_handler!(A, B, C, D);
+
+/// Adapt an extractor-based function into a regular handler
+///
+/// The result can be used anywhere a handler can, alongside handlers with the
+/// usual `(Request, Response, Next)` signature. It ends the chain; it never
+/// calls `next()`.
+pub fn extract<Args, H>(
+    handler: H,
+) -> impl Fn(Request, Response, Next) -> BoxFuture + Send + Sync + 'static
+where
+    H: ExtractHandler<Args>,
+{
+    let handler = Arc::new(handler);
+    move |req: Request, _res: Response, _next: Next| handler.call(&req)
+}
@@ -26,21 +26,46 @@ pub trait IntoHandlers: Sized {
     fn into_handler_vec(self) -> Vec<Handler>;
 
     fn into_chained_handler(self) -> Handler {
-        let handlers = self.into_handler_vec();
-        if handlers.is_empty() {
-            return Arc::new(|_req, res, _next| Box::pin(async move { res }));
-        }
+        chain(self.into_handler_vec())
+    }
+}
+
+/// Combine handlers into one that runs them in order through `next`
+pub fn chain(handlers: Vec<Handler>) -> Handler {
+    if handlers.is_empty() {
+        return Arc::new(|_req, res, _next| Box::pin(async move { res }));
+    }
+
+    Arc::new(move |req, res, final_next| {
+        let handlers = handlers.clone();
+        Box::pin(async move { execute_handlers(req, res, handlers, 0, final_next).await })
+    })
+}
+
+/// Where the running handler sits in its chain, see `Request::chain_position`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChainPosition {
+    /// Zero-based index of the running handler
+    pub index: usize,
+    /// Number of handlers in the chain
+    pub len: usize,
+}
+
+impl ChainPosition {
+    /// Handlers left to run after this one
+    pub fn remaining(&self) -> usize {
+        self.len - self.index - 1
+    }
 
-        Arc::new(move |req, res, final_next| {
-            let handlers = handlers.clone();
-            Box::pin(async move { execute_handlers(req, res, handlers, 0, final_next).await })
-        })
+    /// Whether this is the terminal handler of the chain
+    pub fn is_last(&self) -> bool {
+        self.remaining() == 0
     }
 }
 
 /// Recursively execute a chain of handlers
 fn execute_handlers(
-    req: Request,
+    mut req: Request,
     res: Response,
     handlers: Vec<Handler>,
     index: usize,
@@ -51,6 +76,10 @@ fn execute_handlers(
             return res;
         }
 
+        req.chain_position = Some(ChainPosition {
+            index,
+            len: handlers.len(),
+        });
         let handler = handlers[index].clone();
         let req_clone = req.clone();
         let res_clone = res.clone();
@@ -0,0 +1,150 @@
+use std::fmt;
+
+/// Who may store a response: any cache, or only the client's own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    Private,
+}
+
+/// Directives for a `Cache-Control` response header
+///
+/// Build it with the setters and pass it to `Response::cache_control`:
+///
+/// ```ignore
+/// res.cache_control(CacheControl::new().public().max_age(3600))
+/// ```
+///
+/// Directives that contradict each other are resolved when it is written:
+/// `no-store` is sent alone, since nothing else applies to a response that is
+/// never stored; `immutable` is dropped without a `max-age` or alongside
+/// `no-cache`, which both require revalidation; and `s-maxage` is dropped for
+/// `private` responses, which shared caches never store. `public` and
+/// `private` replace each other, so the last one set wins.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    pub visibility: Option<Visibility>,
+    /// Seconds the response stays fresh
+    pub max_age: Option<u64>,
+    /// Seconds the response stays fresh in shared caches, overriding `max_age`
+    pub s_maxage: Option<u64>,
+    /// Seconds a stale response may be served while it is revalidated
+    pub stale_while_revalidate: Option<u64>,
+    pub no_cache: bool,
+    pub no_store: bool,
+    pub no_transform: bool,
+    pub must_revalidate: bool,
+    pub immutable: bool,
+}
+
+impl CacheControl {
+    /// Create an empty set of directives
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Long-lived caching for assets whose URL changes with their content:
+    /// `public, max-age=31536000, immutable`
+    pub fn immutable_asset() -> Self {
+        Self::new().public().max_age(31_536_000).immutable()
+    }
+
+    /// Let shared caches, such as CDNs, store the response
+    pub fn public(mut self) -> Self {
+        self.visibility = Some(Visibility::Public);
+        self
+    }
+
+    /// Only let the client's own cache store the response
+    pub fn private(mut self) -> Self {
+        self.visibility = Some(Visibility::Private);
+        self
+    }
+
+    /// Keep the response fresh for `seconds`
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Keep the response fresh in shared caches for `seconds`
+    pub fn s_maxage(mut self, seconds: u64) -> Self {
+        self.s_maxage = Some(seconds);
+        self
+    }
+
+    /// Allow serving a stale response for `seconds` while revalidating it
+    pub fn stale_while_revalidate(mut self, seconds: u64) -> Self {
+        self.stale_while_revalidate = Some(seconds);
+        self
+    }
+
+    /// Require revalidation before every reuse of a stored response
+    pub fn no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    /// Forbid storing the response at all
+    pub fn no_store(mut self) -> Self {
+        self.no_store = true;
+        self
+    }
+
+    /// Forbid intermediaries from transforming the body
+    pub fn no_transform(mut self) -> Self {
+        self.no_transform = true;
+        self
+    }
+
+    /// Forbid serving the response stale once it expires
+    pub fn must_revalidate(mut self) -> Self {
+        self.must_revalidate = true;
+        self
+    }
+
+    /// Promise the response never changes while fresh, skipping revalidation on reload
+    pub fn immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.no_store {
+            return f.write_str("no-store");
+        }
+
+        let mut directives = Vec::new();
+        match self.visibility {
+            Some(Visibility::Public) => directives.push("public".to_string()),
+            Some(Visibility::Private) => directives.push("private".to_string()),
+            None => {}
+        }
+        if self.no_cache {
+            directives.push("no-cache".to_string());
+        }
+        if let Some(seconds) = self.max_age {
+            directives.push(format!("max-age={}", seconds));
+        }
+        if let Some(seconds) = self.s_maxage {
+            if self.visibility != Some(Visibility::Private) {
+                directives.push(format!("s-maxage={}", seconds));
+            }
+        }
+        if let Some(seconds) = self.stale_while_revalidate {
+            directives.push(format!("stale-while-revalidate={}", seconds));
+        }
+        if self.must_revalidate {
+            directives.push("must-revalidate".to_string());
+        }
+        if self.no_transform {
+            directives.push("no-transform".to_string());
+        }
+        if self.immutable && self.max_age.is_some() && !self.no_cache {
+            directives.push("immutable".to_string());
+        }
+        f.write_str(&directives.join(", "))
+    }
+}
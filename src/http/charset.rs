@@ -0,0 +1,108 @@
+/// Characters for bytes 0x80–0x9F in windows-1252; `None` where the
+/// encoding leaves the byte undefined, which maps to the C1 control
+const WINDOWS_1252_HIGH: [Option<char>; 32] = [
+    Some('\u{20AC}'),
+    None,
+    Some('\u{201A}'),
+    Some('\u{0192}'),
+    Some('\u{201E}'),
+    Some('\u{2026}'),
+    Some('\u{2020}'),
+    Some('\u{2021}'),
+    Some('\u{02C6}'),
+    Some('\u{2030}'),
+    Some('\u{0160}'),
+    Some('\u{2039}'),
+    Some('\u{0152}'),
+    None,
+    Some('\u{017D}'),
+    None,
+    None,
+    Some('\u{2018}'),
+    Some('\u{2019}'),
+    Some('\u{201C}'),
+    Some('\u{201D}'),
+    Some('\u{2022}'),
+    Some('\u{2013}'),
+    Some('\u{2014}'),
+    Some('\u{02DC}'),
+    Some('\u{2122}'),
+    Some('\u{0161}'),
+    Some('\u{203A}'),
+    Some('\u{0153}'),
+    None,
+    Some('\u{017E}'),
+    Some('\u{0178}'),
+];
+
+/// Single-byte encodings `decode` understands besides UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    Utf8,
+    /// Also used for `iso-8859-1` and `us-ascii` labels, as browsers do:
+    /// it only differs from Latin-1 in the rarely used 0x80–0x9F controls
+    Windows1252,
+    /// Latin-9, Latin-1 with the euro sign and a few French/Finnish letters
+    Iso8859_15,
+}
+
+impl Charset {
+    /// Look up a charset label, ignoring case and surrounding quotes
+    ///
+    /// Supported labels: `utf-8`/`utf8`; `windows-1252`/`cp1252`;
+    /// `iso-8859-1`/`iso8859-1`/`latin1`/`l1`; `us-ascii`/`ascii`;
+    /// `iso-8859-15`/`iso8859-15`/`latin9`/`l9`.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim().trim_matches('"').to_ascii_lowercase();
+        match label.as_str() {
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "windows-1252" | "cp1252" | "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1"
+            | "l1" | "us-ascii" | "ascii" => Some(Self::Windows1252),
+            "iso-8859-15" | "iso8859-15" | "iso_8859-15" | "latin9" | "l9" => {
+                Some(Self::Iso8859_15)
+            }
+            _ => None,
+        }
+    }
+
+    /// Charset named by a `Content-Type` value's `charset` parameter
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        content_type.split(';').skip(1).find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| Self::from_label(value))?
+        })
+    }
+}
+
+/// Decode `bytes` to UTF-8 text
+/// `None` means UTF-8; invalid UTF-8 is replaced lossily
+pub fn decode(bytes: &[u8], charset: Option<Charset>) -> String {
+    match charset {
+        None | Some(Charset::Utf8) => String::from_utf8_lossy(bytes).into_owned(),
+        Some(Charset::Windows1252) => bytes.iter().map(|&b| windows_1252(b)).collect(),
+        Some(Charset::Iso8859_15) => bytes.iter().map(|&b| iso_8859_15(b)).collect(),
+    }
+}
+
+fn windows_1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)].unwrap_or(char::from(byte)),
+        _ => char::from(byte),
+    }
+}
+
+fn iso_8859_15(byte: u8) -> char {
+    match byte {
+        0xA4 => '\u{20AC}',
+        0xA6 => '\u{0160}',
+        0xA8 => '\u{0161}',
+        0xB4 => '\u{017D}',
+        0xB8 => '\u{017E}',
+        0xBC => '\u{0152}',
+        0xBD => '\u{0153}',
+        0xBE => '\u{0178}',
+        _ => char::from(byte),
+    }
+}
@@ -0,0 +1,159 @@
+/// Cookie parsing for requests and `Set-Cookie` values for responses
+use std::{collections::HashMap, fmt};
+
+/// When browsers send a cookie along with cross-site requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    /// Only on requests from the same site
+    Strict,
+    /// Also on top-level navigations from other sites
+    Lax,
+    /// On every request; browsers require `Secure` with it
+    None,
+}
+
+impl SameSite {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+
+/// A cookie and its attributes, written as one `Set-Cookie` header
+///
+/// Pass it to `Response::set_cookie_opts`:
+///
+/// ```ignore
+/// res.set_cookie_opts(
+///     CookieOptions::new("session", &token)
+///         .path("/")
+///         .max_age(3600)
+///         .http_only()
+///         .secure()
+///         .same_site(SameSite::Lax),
+/// )
+/// ```
+///
+/// `;` and line breaks are removed from the name and value, which would
+/// otherwise end the pair or the header. `SameSite=None` always adds
+/// `Secure`, since browsers reject the cookie without it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieOptions {
+    pub name: String,
+    pub value: String,
+    pub path: Option<String>,
+    pub domain: Option<String>,
+    /// Seconds until the cookie expires; 0 removes it right away
+    pub max_age: Option<u64>,
+    pub http_only: bool,
+    pub secure: bool,
+    pub same_site: Option<SameSite>,
+}
+
+impl CookieOptions {
+    /// Create a session cookie without attributes
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Only send the cookie for paths below `path`
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// Also send the cookie to subdomains of `domain`
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// Keep the cookie for `seconds` instead of until the browser closes
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Hide the cookie from scripts
+    pub fn http_only(mut self) -> Self {
+        self.http_only = true;
+        self
+    }
+
+    /// Only send the cookie over HTTPS
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    /// Set when the cookie is sent with cross-site requests
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+}
+
+impl fmt::Display for CookieOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", clean(&self.name), clean(&self.value))?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", clean(path))?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", clean(domain))?;
+        }
+        if let Some(seconds) = self.max_age {
+            write!(f, "; Max-Age={}", seconds)?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if self.secure || self.same_site == Some(SameSite::None) {
+            f.write_str("; Secure")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+fn clean(part: &str) -> String {
+    part.chars()
+        .filter(|c| !matches!(c, ';' | '\r' | '\n'))
+        .collect()
+}
+
+/// Parse a `Cookie` request header into name/value pairs
+///
+/// Pairs are separated by `;`, and a value may itself contain `=`. Quotes
+/// around a value are removed. When a name repeats, the first value wins,
+/// as browsers send the cookie with the most specific path first.
+pub fn parse(header: &str) -> HashMap<String, String> {
+    let mut cookies = HashMap::new();
+    for pair in header.split(';') {
+        let Some((name, value)) = pair.split_once('=') else {
+            continue;
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+        cookies
+            .entry(name.to_string())
+            .or_insert_with(|| value.to_string());
+    }
+    cookies
+}
@@ -0,0 +1,35 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Strong entity tag derived from the content alone, such as `"9f2c…-1024"`
+///
+/// The hash (64-bit FNV-1a) is fixed by this crate rather than by the
+/// standard library, so the same bytes get the same tag across runs, builds
+/// and machines, and caches stay valid across deploys that don't change them.
+pub fn from_content(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("\"{:016x}-{:x}\"", hash, bytes.len())
+}
+
+/// Weak entity tag from a file's size and modification time
+///
+/// Cheap to compute without reading the file, but only as precise as the
+/// filesystem's timestamps, hence weak.
+pub fn from_metadata(len: u64, modified: SystemTime) -> String {
+    let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("W/\"{:x}-{:x}\"", since_epoch.as_nanos(), len)
+}
+
+/// Whether an `If-None-Match` value names `etag`, so a 304 can be sent
+/// Uses weak comparison as the header requires: `W/` prefixes are ignored,
+/// and `*` matches any current representation.
+pub fn none_match(header: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
@@ -0,0 +1,145 @@
+/// Compact header storage for requests and responses
+use std::{borrow::Cow, fmt};
+
+/// Header names interned in this spelling, whatever case a request uses
+const COMMON_NAMES: &[&str] = &[
+    "Host",
+    "User-Agent",
+    "Accept",
+    "Accept-Language",
+    "Accept-Encoding",
+    "Content-Type",
+    "Content-Length",
+    "Authorization",
+    "Connection",
+    "Cookie",
+    "Origin",
+    "Referer",
+    "Cache-Control",
+    "Transfer-Encoding",
+    "If-None-Match",
+    "If-Modified-Since",
+    "Upgrade",
+    "X-Forwarded-For",
+    "X-Forwarded-Proto",
+    "X-Request-Id",
+];
+
+/// Header name/value pairs in arrival order
+///
+/// Backed by a small vector rather than a map: requests carry few headers,
+/// so a linear scan beats hashing, and common names are interned instead of
+/// allocated. Names are matched ignoring ASCII case, as HTTP requires, and
+/// keep the spelling they arrived with (common names take the canonical
+/// one). Inserting an existing name replaces its values; appending keeps
+/// them, for fields such as `Set-Cookie` that repeat.
+#[derive(Clone, Default)]
+pub struct Headers {
+    entries: Vec<(Cow<'static, str>, String)>,
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Look up a header value by name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Every value of a header, in the order they were added
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a String> {
+        self.entries
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// Set a header, replacing any existing values for the same name
+    pub fn insert(&mut self, name: &str, value: String) {
+        match self
+            .entries
+            .iter()
+            .position(|(key, _)| key.eq_ignore_ascii_case(name))
+        {
+            Some(index) => {
+                self.entries[index].1 = value;
+                let mut seen = 0;
+                self.entries.retain(|(key, _)| {
+                    let same = key.eq_ignore_ascii_case(name);
+                    seen += usize::from(same);
+                    !same || seen == 1
+                });
+            }
+            None => self.entries.push((intern(name), value)),
+        }
+    }
+
+    /// Add a value after any existing ones for the same name
+    pub fn append(&mut self, name: &str, value: String) {
+        self.entries.push((intern(name), value));
+    }
+
+    /// Remove a header, returning its value
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self
+            .entries
+            .iter()
+            .position(|(key, _)| key.eq_ignore_ascii_case(name))?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Keep only the headers for which `f` returns true
+    pub fn retain(&mut self, mut f: impl FnMut(&str, &String) -> bool) {
+        self.entries.retain(|(key, value)| f(key, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &String)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Debug for Headers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Borrow a static name for common headers, allocating only for the rest
+fn intern(name: &str) -> Cow<'static, str> {
+    match COMMON_NAMES
+        .iter()
+        .find(|common| common.eq_ignore_ascii_case(name))
+    {
+        Some(common) => Cow::Borrowed(common),
+        None => Cow::Owned(name.to_string()),
+    }
+}
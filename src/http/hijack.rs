@@ -0,0 +1,57 @@
+/// Taking over the connection from the server
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tokio::{net::TcpStream, sync::oneshot};
+
+/// A connection handed over by the server, see `Response::hijack`
+pub struct Connection {
+    /// The client socket, positioned just after the request
+    pub stream: TcpStream,
+    /// Bytes the client sent after the request that the server already read
+    /// Process these before reading from `stream`
+    pub buffered: Vec<u8>,
+}
+
+/// Sending end held by the response until the server hands over the socket
+/// Shared so `Response` stays `Clone`
+#[derive(Clone)]
+pub(crate) struct HijackSlot {
+    sender: Arc<Mutex<Option<oneshot::Sender<Connection>>>>,
+}
+
+impl HijackSlot {
+    pub(crate) fn channel() -> (Self, Hijack) {
+        let (sender, receiver) = oneshot::channel();
+        let slot = Self {
+            sender: Arc::new(Mutex::new(Some(sender))),
+        };
+        (slot, Hijack { receiver })
+    }
+
+    pub(crate) fn take(&self) -> Option<oneshot::Sender<Connection>> {
+        self.sender.lock().ok()?.take()
+    }
+}
+
+/// Resolves to the connection once the server has released it
+///
+/// Fails with `ConnectionAborted` when the server writes a normal response
+/// instead, e.g. because middleware replaced the hijacked response, the
+/// request deadline passed or the client disconnected first.
+pub struct Hijack {
+    receiver: oneshot::Receiver<Connection>,
+}
+
+impl Future for Hijack {
+    type Output = std::io::Result<Connection>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver)
+            .poll(cx)
+            .map_err(|_| std::io::ErrorKind::ConnectionAborted.into())
+    }
+}
@@ -0,0 +1,208 @@
+/// Typed JSON bodies (requires the `serde` feature)
+use crate::{
+    http::response::{IntoResponse, Response},
+    server::config::JsonLimits,
+};
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// Why a request body could not be read as JSON
+#[derive(Debug)]
+pub enum JsonError {
+    /// The request has no body
+    MissingBody,
+    /// The `Content-Type` is missing or not `JsonLimits::content_type`
+    WrongContentType {
+        expected: &'static str,
+        /// The request's `Content-Type`, if it sent one
+        found: Option<String>,
+    },
+    /// The body is larger than `JsonLimits::max_size` bytes
+    TooLarge { limit: usize },
+    /// Arrays and objects nest deeper than `JsonLimits::max_depth`
+    TooDeep { limit: usize, offset: usize },
+    /// The body is not valid JSON or does not match the target type
+    Invalid {
+        /// Dotted path to the offending field (`user.tags[2]`), empty at the root
+        path: String,
+        /// serde's description, e.g. `invalid type: string "x", expected u32`
+        message: String,
+        /// 1-based line and column of the error, 0 when not tied to a position
+        line: usize,
+        column: usize,
+        /// Byte offset of the error in the body
+        offset: Option<usize>,
+    },
+}
+
+impl JsonError {
+    /// JSON `{"errors": [...]}` body describing the error
+    pub fn to_json(&self) -> String {
+        let error = match self {
+            JsonError::MissingBody => serde_json::json!({ "message": "request body is empty" }),
+            JsonError::WrongContentType { .. }
+            | JsonError::TooLarge { .. }
+            | JsonError::TooDeep { .. } => {
+                serde_json::json!({ "message": self.to_string() })
+            }
+            JsonError::Invalid {
+                path,
+                message,
+                line,
+                column,
+                offset,
+            } => serde_json::json!({
+                "path": path,
+                "message": message,
+                "line": line,
+                "column": column,
+                "offset": offset,
+            }),
+        };
+        serde_json::json!({ "errors": [error] }).to_string()
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::MissingBody => write!(f, "request body is empty"),
+            JsonError::WrongContentType {
+                expected,
+                found: Some(found),
+            } => write!(f, "expected Content-Type {}, got {}", expected, found),
+            JsonError::WrongContentType { expected, .. } => {
+                write!(f, "expected Content-Type {}, got none", expected)
+            }
+            JsonError::TooLarge { limit } => write!(f, "body exceeds {} bytes", limit),
+            JsonError::TooDeep { limit, .. } => write!(f, "nesting exceeds depth {}", limit),
+            JsonError::Invalid { path, message, .. } if path.is_empty() => write!(f, "{}", message),
+            JsonError::Invalid { path, message, .. } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// 422 Unprocessable Entity (413 for `TooLarge`, 415 for `WrongContentType`)
+/// with the `to_json` body
+impl IntoResponse for JsonError {
+    fn into_response(self) -> Response {
+        let code = match self {
+            JsonError::TooLarge { .. } => 413,
+            JsonError::WrongContentType { .. } => 415,
+            _ => 422,
+        };
+        Response::new().status(code).json(&self.to_json())
+    }
+}
+
+/// Check a `Content-Type` value against `JsonLimits::content_type`
+pub(crate) fn check_content_type(
+    content_type: Option<&String>,
+    expected: &'static str,
+) -> Result<(), JsonError> {
+    if expected == "*/*" {
+        return Ok(());
+    }
+    let essence = content_type.map(|value| {
+        value
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    });
+    let accepted = essence.as_deref().is_some_and(|essence| {
+        essence.eq_ignore_ascii_case(expected)
+            || (expected.eq_ignore_ascii_case("application/json") && essence.ends_with("+json"))
+    });
+    if accepted {
+        Ok(())
+    } else {
+        Err(JsonError::WrongContentType {
+            expected,
+            found: content_type.cloned(),
+        })
+    }
+}
+
+/// Deserialize `body`, recording where in the input and the type it failed
+pub(crate) fn from_body<T: DeserializeOwned>(
+    body: &str,
+    limits: JsonLimits,
+) -> Result<T, JsonError> {
+    if body.len() > limits.max_size {
+        return Err(JsonError::TooLarge {
+            limit: limits.max_size,
+        });
+    }
+    if let Some(offset) = exceeds_depth(body, limits.max_depth) {
+        return Err(JsonError::TooDeep {
+            limit: limits.max_depth,
+            offset,
+        });
+    }
+
+    let deserializer = &mut serde_json::Deserializer::from_str(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let path = match err.path().to_string() {
+            path if path == "." => String::new(),
+            path => path,
+        };
+        let inner = err.into_inner();
+        let (line, column) = (inner.line(), inner.column());
+        JsonError::Invalid {
+            path,
+            message: message_without_position(&inner),
+            line,
+            column,
+            offset: offset(body, line, column),
+        }
+    })
+}
+
+/// Byte offset of the first bracket nested deeper than `max_depth`
+/// Brackets inside strings are skipped; syntax errors are left to serde
+fn exceeds_depth(body: &str, max_depth: usize) -> Option<usize> {
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    for (offset, byte) in body.bytes().enumerate() {
+        match byte {
+            _ if escaped => escaped = false,
+            b'\\' if in_string => escaped = true,
+            b'"' => in_string = !in_string,
+            _ if in_string => {}
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Some(offset);
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// serde_json appends " at line L column C"; the position is reported separately
+fn message_without_position(err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    match message.rfind(" at line ") {
+        Some(index) if err.line() > 0 => message[..index].to_string(),
+        _ => message,
+    }
+}
+
+/// Byte offset of a 1-based line and column, if they fall inside `body`
+fn offset(body: &str, line: usize, column: usize) -> Option<usize> {
+    if line == 0 {
+        return None;
+    }
+    let line_start: usize = body
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum();
+    Some((line_start + column.saturating_sub(1)).min(body.len()))
+}
@@ -1,2 +1,19 @@
+pub mod cache_control;
+pub mod charset;
+pub mod cookie;
+pub mod etag;
+pub mod headers;
+pub mod hijack;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod json_value;
+pub mod mime;
+pub mod qlist;
+pub mod range;
 pub mod request;
 pub mod response;
+pub mod status;
+pub mod stream;
+pub mod template;
+pub mod timing;
+pub mod url;
@@ -0,0 +1,109 @@
+/// Parse a comma-separated list with optional `;q=` weights, most preferred first
+///
+/// Entries without a weight, or with one that doesn't parse, weigh 1.0;
+/// weights are clamped to `0.0..=1.0` and ties keep header order. Parameters
+/// other than `q` stay part of the value (`text/html;level=1`), and anything
+/// after `q` (accept extensions) is dropped. Empty entries are skipped.
+///
+/// ```ignore
+/// let encodings = qlist::parse("gzip;q=0.8, br, identity;q=0");
+/// assert_eq!(encodings[0], ("br".to_string(), 1.0));
+/// ```
+pub fn parse(header: &str) -> Vec<(String, f32)> {
+    let mut entries: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|entry| {
+            let mut params = entry.split(';').map(str::trim);
+            let mut value = params.next()?.to_string();
+            if value.is_empty() {
+                return None;
+            }
+
+            let mut quality = 1.0;
+            for param in params {
+                match param.split_once('=') {
+                    Some((name, weight)) if name.trim().eq_ignore_ascii_case("q") => {
+                        quality = weight.trim().parse::<f32>().unwrap_or(1.0);
+                        break;
+                    }
+                    _ => {
+                        value.push(';');
+                        value.push_str(param);
+                    }
+                }
+            }
+            let quality = if quality.is_nan() {
+                1.0
+            } else {
+                quality.clamp(0.0, 1.0)
+            };
+            Some((value, quality))
+        })
+        .collect();
+
+    entries.sort_by(|a, b| b.1.total_cmp(&a.1));
+    entries
+}
+
+/// Format entries back into a header value, omitting `q` for weight 1.0
+pub fn format(entries: &[(String, f32)]) -> String {
+    entries
+        .iter()
+        .map(|(value, quality)| {
+            if *quality >= 1.0 {
+                value.clone()
+            } else {
+                format!("{};q={}", value, quality)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Pick the entry of `supported` that an `Accept` header prefers
+///
+/// Each supported type takes the weight of the most specific range matching
+/// it (`text/html` over `text/*` over `*/*`), ignoring media type parameters.
+/// The heaviest type wins and ties keep the order of `supported`; types that
+/// only match with `q=0`, or not at all, are never picked.
+///
+/// ```ignore
+/// let accept = "text/html, application/json;q=0.9";
+/// assert_eq!(qlist::preferred_media_type(accept, &["application/json", "text/html"]), Some("text/html"));
+/// ```
+pub fn preferred_media_type<'a>(accept: &str, supported: &[&'a str]) -> Option<&'a str> {
+    let ranges = parse(accept);
+    let essence = |value: &str| {
+        let essence = value.split(';').next().unwrap_or_default().trim();
+        essence.to_ascii_lowercase()
+    };
+    let weight = |media_type: &str| {
+        let media_type = essence(media_type);
+        let (kind, _) = media_type.split_once('/').unwrap_or((&media_type, ""));
+        ranges
+            .iter()
+            .filter_map(|(range, quality)| {
+                let range = essence(range);
+                let specificity = match range.split_once('/') {
+                    _ if range == media_type => 2,
+                    Some((range_kind, "*")) if range_kind == kind => 1,
+                    Some(("*", "*")) => 0,
+                    _ => return None,
+                };
+                Some((specificity, *quality))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, quality)| quality)
+    };
+
+    let mut best: Option<(&'a str, f32)> = None;
+    for &candidate in supported {
+        match weight(candidate) {
+            Some(quality) if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) => {
+                best = Some((candidate, quality));
+            }
+            _ => {}
+        }
+    }
+    best.map(|(candidate, _)| candidate)
+}
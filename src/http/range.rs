@@ -0,0 +1,78 @@
+/// A satisfiable byte range, `start..=end` within the representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// `Content-Range` value for a 206, such as `bytes 0-99/1234`
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, total)
+    }
+}
+
+/// How to answer a request given its `Range` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeOutcome {
+    /// Send the whole representation with 200
+    Full,
+    /// Send only this range with 206 and `Content-Range`
+    Partial(ByteRange),
+    /// Send 416 with `Content-Range: bytes */<len>`
+    Unsatisfiable,
+}
+
+/// Resolve a `Range` header against a representation of `len` bytes
+///
+/// Handles `bytes=start-end`, `bytes=start-` and suffix ranges
+/// (`bytes=-500`), clamping the end to the last byte. Headers that are
+/// malformed, use another unit or ask for several ranges are ignored and the
+/// full representation is sent, which the spec allows.
+pub fn resolve(header: &str, len: u64) -> RangeOutcome {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return RangeOutcome::Full;
+    };
+    if spec.contains(',') {
+        return RangeOutcome::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeOutcome::Full;
+    };
+    let parse = |value: &str| value.trim().parse::<u64>().ok();
+
+    let range = match (start.trim().is_empty(), end.trim().is_empty()) {
+        // Suffix range: the last `n` bytes
+        (true, false) => match parse(end) {
+            Some(0) => return RangeOutcome::Unsatisfiable,
+            Some(n) if len > 0 => ByteRange {
+                start: len.saturating_sub(n),
+                end: len - 1,
+            },
+            Some(_) => return RangeOutcome::Unsatisfiable,
+            None => return RangeOutcome::Full,
+        },
+        (false, open) => {
+            let Some(start) = parse(start) else {
+                return RangeOutcome::Full;
+            };
+            let end = if open {
+                u64::MAX
+            } else {
+                match parse(end) {
+                    Some(end) if end >= start => end,
+                    _ => return RangeOutcome::Full,
+                }
+            };
+            if start >= len {
+                return RangeOutcome::Unsatisfiable;
+            }
+            ByteRange {
+                start,
+                end: end.min(len - 1),
+            }
+        }
+        (true, true) => return RangeOutcome::Full,
+    };
+    RangeOutcome::Partial(range)
+}
@@ -1,12 +1,41 @@
-use std::collections::HashMap;
+#[cfg(feature = "serde")]
+use crate::http::json::{self, JsonError};
+use crate::{
+    handler::ChainPosition,
+    http::{
+        charset::{self, Charset},
+        cookie,
+        headers::Headers,
+        qlist,
+        timing::Timings,
+        url::{parse_form, parse_query, split_target},
+    },
+    server::{config::JsonLimits, connection::ConnectionContext},
+};
+use std::{collections::HashMap, future::Future, sync::OnceLock, time::Duration};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Debug, Default, Clone)]
 pub struct Request {
     pub method: String,
     pub path: String,
+    pub query: HashMap<String, String>,
+    pub query_string: Option<String>,
     pub version: String,
-    pub headers: HashMap<String, String>,
+    pub headers: Headers,
     pub body: Option<String>,
+    pub(crate) raw_body: Option<Vec<u8>>,
+    pub(crate) shutdown: Option<CancellationToken>,
+    pub(crate) secure: bool,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) chain_position: Option<ChainPosition>,
+    pub(crate) json_limits: JsonLimits,
+    pub(crate) params: HashMap<String, String>,
+    /// Parsed from the `Cookie` header on first use
+    pub(crate) cookies: OnceLock<HashMap<String, String>>,
+    pub(crate) connection: ConnectionContext,
+    pub(crate) timings: Timings,
 }
 
 impl Request {
@@ -14,41 +43,79 @@ impl Request {
         Self {
             method: String::new(),
             path: String::new(),
+            query: HashMap::new(),
+            query_string: None,
             version: String::new(),
-            headers: HashMap::new(),
+            headers: Headers::new(),
             body: None,
+            raw_body: None,
+            shutdown: None,
+            secure: false,
+            deadline: None,
+            chain_position: None,
+            json_limits: JsonLimits::default(),
+            params: HashMap::new(),
+            cookies: OnceLock::new(),
+            connection: ConnectionContext::default(),
+            timings: Timings::default(),
         }
     }
 
     /// Parse raw HTTP request bytes into Request struct
+    ///
+    /// The request line must be exactly `METHOD SP TARGET SP VERSION` with
+    /// single spaces and no leading or trailing whitespace; anything else is
+    /// rejected so the line can't be read two ways. The parser is lenient about
+    /// the rest: bare `\n` line endings are accepted, and input that ends after
+    /// the last header without the terminating blank line is treated as a
+    /// request with no body.
     pub fn from_raw(buffer: &[u8]) -> Option<Self> {
-        let request_str = String::from_utf8_lossy(buffer);
-        let mut lines = request_str.split("\r\n");
-
-        let request_line = lines.next()?;
-        let mut parts = request_line.split_whitespace();
-        let method = parts.next()?.to_string();
-        let path = parts.next()?.to_string();
-        let version = parts.next()?.to_string();
-        let mut headers = HashMap::new();
-
-        for line in &mut lines {
-            if line.is_empty() {
-                break;
-            }
-            if let Some((key, value)) = line.split_once(": ") {
-                headers.insert(key.to_string(), value.to_string());
+        let (head, raw_body) = split_head(buffer);
+        let head = String::from_utf8_lossy(head);
+        let mut lines = head.split('\n').map(|line| line.trim_end_matches('\r'));
+
+        let (method, target, version) = split_request_line(lines.next()?)?;
+        let method = method.to_string();
+        let (path, query_string) = split_target(target);
+        let query = query_string.map(parse_query).unwrap_or_default();
+        let version = version.to_string();
+        let mut headers = Headers::with_capacity(head.bytes().filter(|&b| b == b'\n').count());
+
+        for line in lines {
+            if let Some((key, value)) = line.split_once(':') {
+                headers.insert(key.trim(), value.trim().to_string());
             }
         }
 
-        let body = lines.collect::<Vec<&str>>().join("\r\n");
-        let body = if body.is_empty() { None } else { Some(body) };
+        let body = (!raw_body.is_empty()).then(|| {
+            let charset = headers
+                .get("Content-Type")
+                .and_then(|value| Charset::from_content_type(value));
+            charset::decode(raw_body, charset)
+        });
+        // Only kept when the text differs, i.e. it was transcoded or repaired
+        let raw_body = body
+            .as_ref()
+            .filter(|text| text.as_bytes() != raw_body)
+            .map(|_| raw_body.to_vec());
         Some(Self {
             method,
-            path,
+            path: path.to_string(),
+            query,
+            query_string: query_string.map(str::to_string),
             version,
             headers,
             body,
+            raw_body,
+            shutdown: None,
+            secure: false,
+            deadline: None,
+            chain_position: None,
+            json_limits: JsonLimits::default(),
+            params: HashMap::new(),
+            cookies: OnceLock::new(),
+            connection: ConnectionContext::default(),
+            timings: Timings::default(),
         })
     }
 
@@ -60,11 +127,301 @@ impl Request {
         &self.path
     }
 
+    /// Percent-decoded value of a query-string parameter
+    pub fn query(&self, key: &str) -> Option<&String> {
+        self.query.get(key)
+    }
+
+    /// All percent-decoded query-string parameters
+    /// A key repeated in the query keeps its last value
+    pub fn query_all(&self) -> &HashMap<String, String> {
+        &self.query
+    }
+
+    /// Percent-decoded value of a `:name` segment captured by the route pattern
+    ///
+    /// ```ignore
+    /// app.get("/users/:id", (|req: Request, res: Response, _next: Next| async move {
+    ///     res.send(&format!("user {}", req.param("id").unwrap()))
+    /// },))
+    /// .await;
+    /// ```
+    pub fn param(&self, key: &str) -> Option<&String> {
+        self.params.get(key)
+    }
+
+    /// Parameters captured from the route pattern
+    pub fn params(&self) -> &HashMap<String, String> {
+        &self.params
+    }
+
+    /// Look up a header value, ignoring the case of the name
     pub fn header(&self, key: &str) -> Option<&String> {
         self.headers.get(key)
     }
 
+    /// Value of the cookie `name` from the `Cookie` header
+    ///
+    /// The header is parsed the first time a cookie is read, so later
+    /// changes to `headers` are not seen. See `cookie::parse`.
+    ///
+    /// ```ignore
+    /// let Some(session) = req.cookie("session") else {
+    ///     return res.redirect("/login");
+    /// };
+    /// ```
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies
+            .get_or_init(|| {
+                self.header("Cookie")
+                    .map(|h| cookie::parse(h))
+                    .unwrap_or_default()
+            })
+            .get(name)
+            .map(String::as_str)
+    }
+
+    /// The body as text
+    ///
+    /// Decoded from the `charset` declared in `Content-Type` when it is one
+    /// `Charset::from_label` supports (UTF-8, windows-1252, ISO-8859-1,
+    /// US-ASCII, ISO-8859-15); otherwise read as UTF-8, with invalid
+    /// sequences replaced by U+FFFD.
     pub fn body(&self) -> Option<&String> {
         self.body.as_ref()
     }
+
+    /// The body exactly as received, before any charset decoding
+    pub fn body_bytes(&self) -> Option<&[u8]> {
+        self.raw_body
+            .as_deref()
+            .or_else(|| self.body.as_ref().map(|body| body.as_bytes()))
+    }
+
+    /// Fields of an `application/x-www-form-urlencoded` body, as HTML forms send
+    ///
+    /// Keys and values are percent-decoded with `+` read as a space, and a
+    /// repeated field keeps its last value. `None` when the request has
+    /// another `Content-Type`; a form request without a body has no fields.
+    ///
+    /// ```ignore
+    /// let Some(form) = req.form() else {
+    ///     return res.status(415).send("expected a form");
+    /// };
+    /// let user = form.get("username").cloned().unwrap_or_default();
+    /// ```
+    pub fn form(&self) -> Option<HashMap<String, String>> {
+        let content_type = self.header("Content-Type")?;
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        if !essence.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+            return None;
+        }
+        Some(parse_form(self.body().map_or("", String::as_str)))
+    }
+
+    /// Deserialize the body as JSON (requires the `serde` feature)
+    ///
+    /// On failure the error carries the field path, serde's message (which
+    /// names the expected type) and the byte offset; `into_response()` turns
+    /// it into a 422 with a JSON `errors` array. An empty body, a
+    /// `Content-Type` other than `application/json` (or a `+json` type) and
+    /// bodies over `ServerConfig::json_limits` are rejected before
+    /// deserializing; see `JsonLimits::content_type` to accept other types.
+    #[cfg(feature = "serde")]
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, JsonError> {
+        self.json_with_limits(self.json_limits)
+    }
+
+    /// Like `json`, with limits overriding the server's for this route
+    ///
+    /// ```ignore
+    /// let tree = req.json_with_limits::<Tree>(JsonLimits::new().max_depth(256))?;
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn json_with_limits<T: serde::de::DeserializeOwned>(
+        &self,
+        limits: JsonLimits,
+    ) -> Result<T, JsonError> {
+        let Some(body) = self.body().filter(|body| !body.is_empty()) else {
+            return Err(JsonError::MissingBody);
+        };
+        json::check_content_type(self.header("Content-Type"), limits.content_type)?;
+        json::from_body(body, limits)
+    }
+
+    /// Language tags from `Accept-Language` with their weights, most preferred first
+    /// Entries without `q` weigh 1.0; ties keep header order (see `qlist::parse`)
+    pub fn accept_languages(&self) -> Vec<(String, f32)> {
+        self.header("Accept-Language")
+            .map(|header| qlist::parse(header))
+            .unwrap_or_default()
+    }
+
+    /// Pick the best match from `supported` for the request's `Accept-Language`
+    ///
+    /// Tags match exactly or by primary subtag (`en-US` selects `en`), `*`
+    /// selects the first supported language not rejected with `q=0`.
+    pub fn preferred_language<'a>(&self, supported: &[&'a str]) -> Option<&'a str> {
+        let primary = |tag: &str| {
+            tag.split('-')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase()
+        };
+        let (accepted, rejected): (Vec<_>, Vec<_>) = self
+            .accept_languages()
+            .into_iter()
+            .partition(|(_, quality)| *quality > 0.0);
+
+        accepted.into_iter().find_map(|(tag, _)| {
+            if tag == "*" {
+                return supported
+                    .iter()
+                    .find(|lang| !rejected.iter().any(|(r, _)| lang.eq_ignore_ascii_case(r)))
+                    .copied();
+            }
+            supported
+                .iter()
+                .find(|lang| lang.eq_ignore_ascii_case(&tag))
+                .or_else(|| supported.iter().find(|lang| primary(lang) == primary(&tag)))
+                .copied()
+        })
+    }
+
+    /// Pick the best match from `supported` media types for the request's `Accept`
+    ///
+    /// Without an `Accept` header the first supported type is picked, since
+    /// the client accepts anything. See `qlist::preferred_media_type`.
+    pub fn preferred_type<'a>(&self, supported: &[&'a str]) -> Option<&'a str> {
+        match self.header("Accept") {
+            Some(accept) => qlist::preferred_media_type(accept, supported),
+            None => supported.first().copied(),
+        }
+    }
+
+    /// Whether the request arrived over a TLS connection
+    /// HTTPS terminated by a proxy is not reflected here (see `X-Forwarded-Proto`)
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Instant by which the whole request must be answered, if any
+    ///
+    /// Set from `ServerConfig::request_timeout` and the client's
+    /// `X-Request-Timeout` header (seconds), whichever is sooner.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Time left before the deadline, zero once it has passed
+    ///
+    /// Bound downstream calls with it so they give up before the request does:
+    ///
+    /// ```ignore
+    /// let budget = req.remaining().unwrap_or(Duration::from_secs(30));
+    /// let user = tokio::time::timeout(budget, db.fetch_user(id)).await;
+    /// ```
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Position of the running handler within its route's handler chain
+    ///
+    /// Set for handlers registered as a tuple (and group middleware); `None`
+    /// in global middleware. With nested chains it describes the innermost.
+    ///
+    /// ```ignore
+    /// if req.chain_position().is_some_and(|pos| pos.is_last()) { /* terminal */ }
+    /// ```
+    pub fn chain_position(&self) -> Option<ChainPosition> {
+        self.chain_position
+    }
+
+    /// State shared by the requests of this connection
+    ///
+    /// Only persists across requests when `ServerConfig::keep_alive` lets
+    /// a connection carry more than one. Requests built by hand get an empty,
+    /// unshared context.
+    pub fn connection(&self) -> &ConnectionContext {
+        &self.connection
+    }
+
+    /// Run the future built by `f`, recording its duration as a span
+    ///
+    /// With `ServerConfig::server_timing` on, spans from every middleware and
+    /// handler are sent back in a `Server-Timing` header, which browser dev
+    /// tools show next to the request. Otherwise the future just runs.
+    ///
+    /// ```ignore
+    /// let user = req.time("db_query", || async { db.find_user(id).await }).await;
+    /// ```
+    pub async fn time<F, Fut, T>(&self, name: &str, f: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        self.timings.time(name, f).await
+    }
+
+    /// Spans recorded for this request, see `time`
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Token that is cancelled when the server begins shutting down
+    ///
+    /// Long-running handlers (SSE, streaming, polling) should select on it in
+    /// their loops so they can finish cleanly instead of being dropped:
+    ///
+    /// ```ignore
+    /// let shutdown = req.shutdown_signal();
+    /// loop {
+    ///     tokio::select! {
+    ///         _ = shutdown.cancelled() => break,
+    ///         _ = interval.tick() => { /* produce the next event */ }
+    ///     }
+    /// }
+    /// ```
+    pub fn shutdown_signal(&self) -> CancellationToken {
+        self.shutdown.clone().unwrap_or_default()
+    }
+}
+
+/// Split a request line into method, target and version
+/// Exactly three non-empty tokens separated by single spaces, nothing else
+fn split_request_line(line: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = line.split(' ');
+    let tokens = (parts.next()?, parts.next()?, parts.next()?);
+    let (method, target, version) = tokens;
+    let well_formed = parts.next().is_none()
+        && [method, target, version]
+            .iter()
+            .all(|token| !token.is_empty() && !token.contains(char::is_whitespace));
+    well_formed.then_some(tokens)
+}
+
+/// Split raw request bytes into the head (request line and headers) and body
+/// Falls back to bare `\n\n` separators, and to no body when none is present
+fn split_head(raw: &[u8]) -> (&[u8], &[u8]) {
+    let find = |separator: &[u8]| {
+        raw.windows(separator.len())
+            .position(|window| window == separator)
+            .map(|index| (index, separator.len()))
+    };
+    let separator = [find(b"\r\n\r\n"), find(b"\n\n")]
+        .into_iter()
+        .flatten()
+        .min_by_key(|(index, _)| *index);
+
+    match separator {
+        Some((index, len)) => (&raw[..index], &raw[index + len..]),
+        None => {
+            let end = raw
+                .iter()
+                .rposition(|&b| b != b'\r' && b != b'\n')
+                .map_or(0, |last| last + 1);
+            (&raw[..end], &[])
+        }
+    }
 }
@@ -1,11 +1,43 @@
-use std::collections::HashMap;
+use crate::{
+    errors::{BoxError, HandlerError},
+    http::{
+        cache_control::CacheControl,
+        cookie::CookieOptions,
+        headers::Headers,
+        hijack::{Hijack, HijackSlot},
+        json_value::JsonValue,
+        mime,
+        status::StatusCode,
+        stream::{BodySender, StreamBody},
+        template,
+    },
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 #[derive(Clone)]
 pub struct Response {
     pub status_code: u16,
     pub status_text: String,
-    pub headers: HashMap<String, String>,
-    pub body: Option<String>,
+    pub headers: Headers,
+    /// Body written as-is: text from `send`, or bytes from `send_bytes`
+    pub body: Option<Cow<'static, [u8]>>,
+    pub error: Option<HandlerError>,
+    pub(crate) file: Option<FileBody>,
+    pub(crate) stream: Option<StreamBody>,
+    pub(crate) hijack: Option<HijackSlot>,
+    /// Set on the fallback response when no route matched the request
+    pub(crate) unmatched: bool,
+}
+
+/// A file streamed from disk when the response is written
+#[derive(Clone)]
+pub(crate) struct FileBody {
+    pub(crate) path: PathBuf,
+    pub(crate) len: u64,
 }
 
 impl Response {
@@ -13,54 +45,692 @@ impl Response {
         Self {
             status_code: 200,
             status_text: "OK".to_string(),
-            headers: HashMap::new(),
+            headers: Headers::new(),
             body: None,
+            error: None,
+            file: None,
+            stream: None,
+            hijack: None,
+            unmatched: false,
+        }
+    }
+
+    /// Create a response with the given status and a `"<code> <reason>"` text body
+    pub fn from_status(code: u16) -> Self {
+        Self::error(code, &format!("{} {}", code, reason_phrase(code)))
+    }
+
+    /// Create a response with the given status and a custom text body
+    pub fn error(code: u16, message: &str) -> Self {
+        Self::new().status(code).text(message)
+    }
+
+    /// Set the status from a raw code or a `StatusCode` constant
+    pub fn status<S: Into<StatusCode>>(mut self, status: S) -> Self {
+        let status = status.into();
+        self.status_code = status.as_u16();
+        self.status_text = status.reason().to_string();
+        self
+    }
+
+    /// Set the reason phrase sent after the status code, keeping the code
+    ///
+    /// For passing a non-standard upstream status line through verbatim. Call
+    /// it after `status`, which resets the phrase from the status table. Line
+    /// breaks are stripped; an empty phrase falls back to the table.
+    pub fn reason(mut self, phrase: &str) -> Self {
+        self.status_text = strip_line_breaks(phrase);
+        self
+    }
+
+    /// Reason phrase written in the status line
+    /// The phrase from `reason` or `status`, or the table's when it is empty.
+    pub fn reason_text(&self) -> &str {
+        if self.status_text.is_empty() {
+            reason_phrase(self.status_code)
+        } else {
+            &self.status_text
         }
     }
 
-    pub fn status(mut self, code: u16) -> Self {
-        self.status_code = code;
-        self.status_text = match code {
-            200 => "OK",
-            201 => "Created",
-            400 => "Bad Request",
-            404 => "Not Found",
-            500 => "Internal Server Error",
-            _ => "Unknown",
+    /// Set `Cache-Control` from typed directives, see `CacheControl`
+    /// Empty directives remove the header instead of sending an empty value.
+    pub fn cache_control(self, directives: CacheControl) -> Self {
+        let value = directives.to_string();
+        if value.is_empty() {
+            self.remove_header("Cache-Control")
+        } else {
+            self.set_header("Cache-Control", &value)
         }
-        .to_string();
-        return self;
     }
 
-    pub fn set_header(mut self, key: &str, value: &str) -> Self {
-        self.headers.insert(key.to_string(), value.to_string());
-        return self;
+    /// Set a header, stripping CR and LF so values can't inject extra lines
+    ///
+    /// Replaces every existing value for the name, ignoring its case.
+    pub fn set_header(self, key: &str, value: &str) -> Self {
+        self.remove_header(key).append_header(key, value)
+    }
+
+    /// Add a header line, keeping any existing ones with the same name
+    ///
+    /// For fields that may repeat, such as `Set-Cookie` or `Link`. Line breaks
+    /// are stripped as with `set_header`.
+    ///
+    /// ```ignore
+    /// res.append_header("Link", "</app.css>; rel=preload")
+    ///     .append_header("Link", "</app.js>; rel=preload")
+    /// ```
+    pub fn append_header(mut self, key: &str, value: &str) -> Self {
+        self.headers
+            .append(&strip_line_breaks(key), strip_line_breaks(value));
+        self
+    }
+
+    /// Set several headers at once, each as with `set_header`
+    ///
+    /// ```ignore
+    /// res.set_headers([("Cache-Control", "no-store"), ("X-Frame-Options", "DENY")])
+    /// ```
+    pub fn set_headers<I, K, V>(self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        headers.into_iter().fold(self, |res, (key, value)| {
+            res.set_header(key.as_ref(), value.as_ref())
+        })
+    }
+
+    /// Create a 200 response that starts with the given headers
+    pub fn with_headers<I, K, V>(headers: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        Self::new().set_headers(headers)
+    }
+
+    /// Remove a header, ignoring the case of the name
+    pub fn remove_header(mut self, key: &str) -> Self {
+        self.headers.retain(|k, _| !k.eq_ignore_ascii_case(key));
+        self
+    }
+
+    /// Remove every header
+    pub fn clear_headers(mut self) -> Self {
+        self.headers.clear();
+        self
+    }
+
+    /// Set a session cookie without attributes, see `set_cookie_opts`
+    pub fn set_cookie(self, name: &str, value: &str) -> Self {
+        self.set_cookie_opts(CookieOptions::new(name, value))
+    }
+
+    /// Set a cookie with attributes such as `Max-Age` and `HttpOnly`
+    ///
+    /// Each call adds its own `Set-Cookie` header, so several cookies can be
+    /// set on one response.
+    pub fn set_cookie_opts(self, cookie: CookieOptions) -> Self {
+        self.append_header("Set-Cookie", &cookie.to_string())
+    }
+
+    /// Remove the body, whether in memory, a file or a stream, keeping status and headers
+    ///
+    /// Content-Length is derived from the body when the response is written,
+    /// so the cleared response goes out with `Content-Length: 0`, except for
+    /// 1xx, 204 and 304 statuses, which are sent without any framing header.
+    /// For a 304, clear the body but keep validators such as `ETag`:
+    ///
+    /// ```ignore
+    /// res.status(304).clear_body().remove_header("Content-Type")
+    /// ```
+    pub fn clear_body(mut self) -> Self {
+        self.body = None;
+        self.file = None;
+        self.stream = None;
+        self
+    }
+
+    /// Redirect to `location` with 302 Found and an empty body
+    ///
+    /// ```ignore
+    /// if session.is_none() {
+    ///     return res.redirect("/login");
+    /// }
+    /// ```
+    pub fn redirect(self, location: &str) -> Self {
+        self.redirect_with_status(302, location)
+    }
+
+    /// Redirect with another status, such as 301, 303, 307 or 308
+    ///
+    /// Only 3xx codes are meaningful; others are sent as given, and clients
+    /// will ignore the `Location` header.
+    pub fn redirect_with_status<S: Into<StatusCode>>(self, status: S, location: &str) -> Self {
+        self.status(status)
+            .set_header("Location", location)
+            .clear_body()
+    }
+
+    /// Mark the response as failed with the given error
+    ///
+    /// The status becomes 500 unless an error status was already set. The
+    /// app's `on_error` hook, if registered, builds the final response.
+    pub fn fail<E>(self, error: E) -> Self
+    where
+        E: Into<BoxError>,
+    {
+        let mut res = if self.status_code < 400 {
+            self.status(500)
+        } else {
+            self
+        };
+        res.error = Some(HandlerError::new(error));
+        res
     }
 
-    pub fn send(mut self, body: &str) -> Self {
-        self.body = Some(body.to_string());
-        return self;
+    /// Look up a header value, ignoring the case of the name
+    ///
+    /// Returns the first value when the header was appended more than once.
+    pub fn header(&self, key: &str) -> Option<&String> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
     }
 
-    pub fn json(mut self, data: &str) -> Self {
+    /// Every value of a header in the order added, ignoring the case of the name
+    pub fn header_all(&self, key: &str) -> Vec<&String> {
         self.headers
-            .insert("Content-Type".into(), "application/json".into());
-        self.body = Some(data.to_string());
-        return self;
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+            .collect()
+    }
+
+    pub fn send(self, body: &str) -> Self {
+        self.with_body(Cow::Owned(body.as_bytes().to_vec()))
+    }
+
+    /// The body as text, `None` when there is none or it is not UTF-8
+    ///
+    /// File and streamed bodies are not in memory and read as `None`.
+    pub fn body_text(&self) -> Option<&str> {
+        std::str::from_utf8(self.body.as_deref()?).ok()
+    }
+
+    /// Append text to the body, creating it if absent
+    ///
+    /// `send`, `json`, `text` and `html` replace the body; this adds to it, so
+    /// a body set by `send` or `send_bytes` can be extended and vice versa.
+    /// Content-Length is computed when the response is built.
+    ///
+    /// ```ignore
+    /// res.status(200).append_body(header).append_body(content).append_body(footer)
+    /// ```
+    pub fn append_body(self, chunk: &str) -> Self {
+        self.append_bytes(chunk.as_bytes())
+    }
+
+    /// Append bytes to the body unchanged, see `append_body`
+    pub fn append_bytes(mut self, chunk: &[u8]) -> Self {
+        self.body
+            .get_or_insert_with(Default::default)
+            .to_mut()
+            .extend_from_slice(chunk);
+        self.file = None;
+        self.stream = None;
+        self
+    }
+
+    /// Stream a file from disk as the body
+    ///
+    /// The file is copied to the socket through a fixed-size buffer (see
+    /// `ServerConfig::file_chunk_size`), so memory use does not grow with the
+    /// file size. Content-Type is guessed from the extension unless already
+    /// set. Responds with 404 when the path is missing or not a regular file.
+    ///
+    /// ```ignore
+    /// app.get("/download", (|_req, res: Response, _next| async move {
+    ///     res.send_file("assets/big.iso").await
+    /// },))
+    /// .await;
+    /// ```
+    pub async fn send_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        let path = path.as_ref();
+        let metadata = match tokio::fs::metadata(path).await {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => return Self::from_status(404),
+        };
+
+        if self.header("Content-Type").is_none() {
+            self = self.content_type(mime::from_path(path));
+        }
+        self.body = None;
+        self.stream = None;
+        self.file = Some(FileBody {
+            path: path.to_path_buf(),
+            len: metadata.len(),
+        });
+        self
+    }
+
+    /// Send bytes as the body, unchanged
+    ///
+    /// Unlike `send`, the bytes need not be UTF-8, so binary data such as an
+    /// image or a PDF arrives intact. Content-Type defaults to
+    /// `application/octet-stream`.
+    ///
+    /// ```ignore
+    /// let png = tokio::fs::read("avatar.png").await?;
+    /// res.content_type("image/png").send_bytes(png)
+    /// ```
+    pub fn send_bytes(self, bytes: Vec<u8>) -> Self {
+        self.binary(Cow::Owned(bytes))
+    }
+
+    /// Send static bytes as the body without copying them, see `send_bytes`
+    pub fn send_static(self, bytes: &'static [u8]) -> Self {
+        self.binary(Cow::Borrowed(bytes))
+    }
+
+    fn binary(mut self, bytes: Cow<'static, [u8]>) -> Self {
+        if self.header("Content-Type").is_none() {
+            self = self.content_type("application/octet-stream");
+        }
+        self.with_body(bytes)
+    }
+
+    /// Replace the body of any kind with `body`
+    fn with_body(mut self, body: Cow<'static, [u8]>) -> Self {
+        self.body = Some(body);
+        self.file = None;
+        self.stream = None;
+        self
+    }
+
+    pub fn json(self, data: &str) -> Self {
+        self.content_type("application/json").send(data)
+    }
+
+    /// Send a JSON object built from `fields`, escaping strings
+    /// Works without the `serde` feature, see `JsonValue`
+    pub fn json_object(self, fields: &[(&str, JsonValue)]) -> Self {
+        self.json(&JsonValue::object(fields).to_string())
     }
 
-    pub fn build(&self) -> String {
-        let body_str = self.body.clone().unwrap_or_default();
-        let content_length = body_str.len();
+    /// Send a plain text body
+    pub fn text(self, body: &str) -> Self {
+        self.content_type("text/plain").send(body)
+    }
+
+    /// Send an HTML body
+    pub fn html(self, body: &str) -> Self {
+        self.content_type("text/html").send(body)
+    }
+
+    /// Send an HTML body from a template, see `template::render`
+    ///
+    /// ```ignore
+    /// let vars = HashMap::from([("name", req.query("name").cloned().unwrap_or_default())]);
+    /// res.render("<h1>Hello, {{name}}!</h1>", &vars)
+    /// ```
+    pub fn render(self, template: &str, vars: &HashMap<&str, String>) -> Self {
+        self.html(&template::render(template, vars))
+    }
+
+    /// Set the Content-Type, adding `charset=utf-8` to text types without one
+    pub fn content_type(mut self, value: &str) -> Self {
+        self.headers
+            .retain(|k, _| !k.eq_ignore_ascii_case("Content-Type"));
+        self.headers
+            .append("Content-Type", with_utf8_charset(value));
+        self
+    }
+
+    /// Stream the body with chunked transfer encoding
+    ///
+    /// Returns the response to hand back and a sender that produces the body,
+    /// usually from a spawned task. The body ends when the sender is dropped
+    /// or `finish_with_trailers` is called.
+    ///
+    /// ```ignore
+    /// let (res, body) = res.status(200).trailer("X-Checksum").chunked();
+    /// tokio::spawn(async move {
+    ///     for part in parts {
+    ///         if body.send(part).await.is_err() { return; }
+    ///     }
+    ///     let _ = body.finish_with_trailers(&[("X-Checksum", &sum)]).await;
+    /// });
+    /// res
+    /// ```
+    pub fn chunked(mut self) -> (Self, BodySender) {
+        let (stream, sender) = StreamBody::channel();
+        self.body = None;
+        self.file = None;
+        self.stream = Some(stream);
+        (self, sender)
+    }
+
+    /// Take over the connection instead of sending this response
+    ///
+    /// Return the response from the handler and await the `Hijack` in a
+    /// spawned task: once the chain has finished, the server writes nothing
+    /// and hands the socket over as a `Connection`, including any bytes the
+    /// client sent early. The task then owns the socket and writes its own
+    /// status line (e.g. `101 Switching Protocols`); the server never reads
+    /// from or closes it again. Hijacked connections are not tracked by
+    /// graceful shutdown, so watch `Request::shutdown_signal` to close them.
+    ///
+    /// ```ignore
+    /// let (res, hijack) = res.hijack();
+    /// tokio::spawn(async move {
+    ///     let Ok(mut conn) = hijack.await else { return };
+    ///     let _ = conn.stream.write_all(b"HTTP/1.1 101 Switching Protocols\r\n...").await;
+    /// });
+    /// res
+    /// ```
+    pub fn hijack(mut self) -> (Self, Hijack) {
+        let (slot, hijack) = HijackSlot::channel();
+        self.hijack = Some(slot);
+        (self, hijack)
+    }
+
+    /// Stream `items` as a JSON array without buffering it (requires `serde`)
+    ///
+    /// Writes `[`, the items separated by commas as the stream produces
+    /// them, then `]`, with chunked transfer encoding; an empty stream sends
+    /// `[]`. Items are batched into chunks of up to 8 KiB while more are
+    /// ready. An item that fails to serialize aborts the body, see
+    /// `BodySender::abort`.
+    ///
+    /// ```ignore
+    /// res.json_stream(futures::stream::iter(rows))
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn json_stream<S>(self, items: S) -> Self
+    where
+        S: futures::Stream + Send + 'static,
+        S::Item: serde::Serialize + Send,
+    {
+        use futures::StreamExt;
+        self.try_json_stream(items.map(Ok::<_, std::convert::Infallible>))
+    }
+
+    /// Like `json_stream` for a fallible stream, such as rows from a cursor
+    /// An `Err` item is logged and aborts the body, since the status and
+    /// headers have already been sent
+    #[cfg(feature = "serde")]
+    pub fn try_json_stream<S, T, E>(self, items: S) -> Self
+    where
+        S: futures::Stream<Item = Result<T, E>> + Send + 'static,
+        T: serde::Serialize + Send,
+        E: std::fmt::Display + Send,
+    {
+        use futures::{FutureExt, StreamExt};
+        const BATCH: usize = 8 * 1024;
+
+        let (res, body) = self.content_type("application/json").chunked();
+        tokio::spawn(async move {
+            let mut items = std::pin::pin!(items);
+            let mut chunk = b"[".to_vec();
+            let mut first = true;
+            loop {
+                // Flush what is buffered before waiting on a slow stream
+                let next = match items.next().now_or_never() {
+                    Some(next) => next,
+                    None => {
+                        if body.send(std::mem::take(&mut chunk)).await.is_err() {
+                            return;
+                        }
+                        items.next().await
+                    }
+                };
+                let Some(item) = next else { break };
+                if !first {
+                    chunk.push(b',');
+                }
+                first = false;
+                let written = item.map_err(|err| err.to_string()).and_then(|item| {
+                    serde_json::to_writer(&mut chunk, &item).map_err(|err| err.to_string())
+                });
+                if let Err(err) = written {
+                    eprintln!("❌ [Error] JSON stream aborted: {}", err);
+                    body.abort().await;
+                    return;
+                }
+                if chunk.len() >= BATCH && body.send(std::mem::take(&mut chunk)).await.is_err() {
+                    return;
+                }
+            }
+            chunk.push(b']');
+            let _ = body.send(chunk).await;
+        });
+        res
+    }
+
+    /// Declare a trailer field sent after the last chunk (adds to `Trailer`)
+    pub fn trailer(mut self, name: &str) -> Self {
+        let declared = match self.header("Trailer") {
+            Some(existing) => format!("{}, {}", existing, name),
+            None => name.to_string(),
+        };
+        self.headers
+            .retain(|k, _| !k.eq_ignore_ascii_case("Trailer"));
+        self.headers.append("Trailer", declared);
+        self
+    }
+
+    /// Transform the whole body and keep any explicit `Content-Length` in sync
+    ///
+    /// Intended for post-processing middleware that rewrites the body returned
+    /// by `next()` (minification, templating, script injection):
+    ///
+    /// ```ignore
+    /// app.use_middleware(|_req: Request, _res: Response, next: Next| async move {
+    ///     next().await.map_body(|body| body.to_ascii_uppercase())
+    /// })
+    /// .await;
+    /// ```
+    ///
+    /// An absent body is passed to `f` as an empty buffer, and the bytes `f`
+    /// returns are kept unchanged, so it may produce binary output.
+    pub fn map_body<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(Vec<u8>) -> Vec<u8>,
+    {
+        let body = f(self.body.take().map(Cow::into_owned).unwrap_or_default());
+
+        if self.header("Content-Length").is_some() {
+            self = self.set_header("Content-Length", &body.len().to_string());
+        }
+
+        self.body = Some(Cow::Owned(body));
+        self
+    }
+
+    /// The whole response as written to the socket, with `Content-Length`
+    /// counting the body's bytes; a `Content-Length` set with `set_header` is
+    /// replaced. File and streamed bodies are not included.
+    pub fn build(&self) -> Vec<u8> {
+        let body = match &self.body {
+            Some(body) if self.allows_body() => body,
+            _ => &[][..],
+        };
+        let mut out = self.head(Some(body.len() as u64)).into_bytes();
+        out.extend_from_slice(body);
+        out
+    }
+
+    /// Length of the body as it would be written, or `None` for a stream
+    pub(crate) fn content_length(&self) -> Option<u64> {
+        if !self.allows_body() {
+            return Some(0);
+        }
+        if self.stream.is_some() {
+            return None;
+        }
+        let length = match (&self.body, &self.file) {
+            (Some(body), _) => body.len() as u64,
+            (None, Some(file)) => file.len,
+            (None, None) => 0,
+        };
+        Some(length)
+    }
+
+    /// Describe the first part that can't be written as valid HTTP, if any
+    ///
+    /// `set_header` strips line breaks, but `headers` and `status_text` are
+    /// public, and a name can still hold spaces or colons. The server checks
+    /// this before writing and sends a plain 500 instead of a malformed head.
+    pub fn invalid_part(&self) -> Option<String> {
+        if !(100..=999).contains(&self.status_code) {
+            return Some(format!("status code {}", self.status_code));
+        }
+        if !is_field_value(&self.status_text) {
+            return Some(format!("status text {:?}", self.status_text));
+        }
+        self.headers.iter().find_map(|(name, value)| {
+            if !is_token(name) {
+                Some(format!("header name {:?}", name))
+            } else if !is_field_value(value) {
+                Some(format!("value of header {}", name))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Status line and headers, up to and including the blank line
+    ///
+    /// Without a length the body is announced as chunked; statuses that
+    /// can't have a body (see `allows_body`) get neither framing header. The framing header
+    /// is always derived from the actual body; any `Content-Length` or
+    /// `Transfer-Encoding` set on the response is dropped, since a middleware
+    /// may have changed the body after it was set.
+    pub(crate) fn head(&self, content_length: Option<u64>) -> String {
         let mut headers = String::new();
 
-        for (k, v) in &self.headers {
+        for (k, v) in self.headers.iter() {
+            if k.eq_ignore_ascii_case("Content-Length")
+                || k.eq_ignore_ascii_case("Transfer-Encoding")
+            {
+                continue;
+            }
             headers.push_str(&format!("{}: {}\r\n", k, v));
         }
 
-        return format!(
-            "HTTP/1.1 {} {}\r\nContent-Length: {}\r\n{}\r\n{}",
-            self.status_code, self.status_text, content_length, headers, body_str
-        );
+        if let Some(content_type) = self.implied_content_type() {
+            headers.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+
+        // Responses that can't have a body get no framing header at all
+        let framing = match content_length {
+            _ if !self.allows_body() => String::new(),
+            Some(length) => format!("Content-Length: {}\r\n", length),
+            None => "Transfer-Encoding: chunked\r\n".to_string(),
+        };
+        format!(
+            "HTTP/1.1 {} {}\r\n{}{}\r\n",
+            self.status_code,
+            self.reason_text(),
+            framing,
+            headers
+        )
+    }
+
+    /// Content-Type sent for an in-memory body that has none set
+    ///
+    /// UTF-8 text, or `application/octet-stream` when the bytes aren't UTF-8.
+    pub(crate) fn implied_content_type(&self) -> Option<String> {
+        if self.header("Content-Type").is_some() {
+            return None;
+        }
+        let body = self.body.as_deref()?;
+        Some(match std::str::from_utf8(body) {
+            Ok(_) => with_utf8_charset("text/plain"),
+            Err(_) => "application/octet-stream".to_string(),
+        })
+    }
+
+    /// Whether the status permits a body; 1xx, 204 and 304 never have one
+    ///
+    /// A 304 could repeat the selected representation's length, but leaving
+    /// it out is always valid and never claims the cached copy is empty.
+    pub(crate) fn allows_body(&self) -> bool {
+        !matches!(self.status_code, 100..=199 | 204 | 304)
+    }
+}
+
+/// Conversion into a response, for errors and values handlers can return as-is
+///
+/// ```ignore
+/// match req.json::<NewUser>() {
+///     Ok(user) => res.status(201).json(&create(user)),
+///     Err(err) => err.into_response(),
+/// }
+/// ```
+pub trait IntoResponse {
+    fn into_response(self) -> Response;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+/// Standard reason phrase for a status code, see `StatusCode::reason`
+pub fn reason_phrase(code: u16) -> &'static str {
+    StatusCode::from(code).reason()
+}
+
+/// Whether `name` is a non-empty HTTP token, as header names must be
+fn is_token(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Whether `value` has no control characters other than tab
+fn is_field_value(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|b| b == b'\t' || !(b < 0x20 || b == 0x7f))
+}
+
+fn strip_line_breaks(value: &str) -> String {
+    value.replace(['\r', '\n'], "")
+}
+
+/// Append `; charset=utf-8` to text-ish content types that declare no charset
+fn with_utf8_charset(content_type: &str) -> String {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let is_text =
+        essence.starts_with("text/") || essence == "application/json" || essence.ends_with("+json");
+    let has_charset = content_type
+        .split(';')
+        .skip(1)
+        .any(|param| param.trim().to_ascii_lowercase().starts_with("charset="));
+
+    if is_text && !has_charset {
+        format!("{}; charset=utf-8", content_type)
+    } else {
+        content_type.to_string()
+    }
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,78 @@
+/// Chunked streaming bodies
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Chunks buffered between the producer and the connection before `send` waits
+const CHANNEL_CAPACITY: usize = 16;
+
+/// One step of a streamed body
+pub(crate) enum Frame {
+    Data(Vec<u8>),
+    Trailers(Vec<(String, String)>),
+    /// Stop without the final chunk and close the connection
+    Abort,
+}
+
+/// Receiving end of a streamed body, taken once by the connection writer
+/// Shared so `Response` stays `Clone`
+#[derive(Clone)]
+pub(crate) struct StreamBody {
+    receiver: Arc<Mutex<Option<mpsc::Receiver<Frame>>>>,
+}
+
+impl StreamBody {
+    pub(crate) fn channel() -> (Self, BodySender) {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let body = Self {
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+        };
+        (body, BodySender { sender })
+    }
+
+    pub(crate) fn take(&self) -> Option<mpsc::Receiver<Frame>> {
+        self.receiver.lock().ok()?.take()
+    }
+}
+
+/// Producer side of a chunked response, see `Response::chunked`
+///
+/// Dropping the sender ends the body. `send` fails once the client has gone
+/// away, which is the producer's cue to stop. A producer that fails midway
+/// should `abort` instead, so the client can't mistake a truncated body for a
+/// complete one.
+pub struct BodySender {
+    sender: mpsc::Sender<Frame>,
+}
+
+impl BodySender {
+    /// Send one chunk; empty chunks are skipped since they would end the body
+    pub async fn send<B: Into<Vec<u8>>>(&self, chunk: B) -> std::io::Result<()> {
+        let chunk = chunk.into();
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        self.sender
+            .send(Frame::Data(chunk))
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// End the body, writing `trailers` after the final chunk
+    /// Declare each name up front with `Response::trailer`
+    pub async fn finish_with_trailers(self, trailers: &[(&str, &str)]) -> std::io::Result<()> {
+        let trailers = trailers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self.sender
+            .send(Frame::Trailers(trailers))
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// End the body as failed: the final chunk is never written and the
+    /// connection is closed, which clients report as an incomplete response
+    pub async fn abort(self) {
+        let _ = self.sender.send(Frame::Abort).await;
+    }
+}
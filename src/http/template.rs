@@ -0,0 +1,52 @@
+/// Minimal `{{name}}` templating for HTML responses
+use std::collections::HashMap;
+
+/// Substitute `{{name}}` placeholders with HTML-escaped values
+///
+/// Whitespace inside the braces is ignored (`{{ name }}`). Placeholders
+/// without a value are left in the output as written, so a typo shows up
+/// in the page instead of silently vanishing. `{{{` produces a literal `{{`.
+///
+/// ```ignore
+/// let vars = HashMap::from([("name", "<Bob>".to_string())]);
+/// assert_eq!(render("Hi {{name}} {{{x}}", &vars), "Hi &lt;Bob&gt; {{x}}");
+/// ```
+pub fn render(template: &str, vars: &HashMap<&str, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{{{") {
+            out.push_str("{{");
+            rest = after;
+            continue;
+        }
+        let Some(end) = rest.find("}}") else {
+            break;
+        };
+        match vars.get(rest[2..end].trim()) {
+            Some(value) => out.push_str(&escape_html(value)),
+            None => out.push_str(&rest[..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Escape `<`, `>`, `&`, `"` and `'` for HTML text and attribute values
+pub fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
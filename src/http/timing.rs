@@ -0,0 +1,101 @@
+/// Request-scoped timing spans reported through `Server-Timing`
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// A finished span: its name and how long it took
+type Span = (String, Duration);
+
+/// Named durations recorded while handling one request
+///
+/// Every clone of a request shares the same collector, so spans recorded in
+/// middleware and handlers all end up in one `Server-Timing` header. Unless
+/// `ServerConfig::server_timing` is on, the collector is disabled: nothing is
+/// recorded and timing a future costs nothing.
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    spans: Option<Arc<Mutex<Vec<Span>>>>,
+}
+
+impl Timings {
+    /// A collector that records spans
+    pub(crate) fn enabled() -> Self {
+        Self {
+            spans: Some(Arc::default()),
+        }
+    }
+
+    /// Whether spans are being recorded for this request
+    pub fn is_enabled(&self) -> bool {
+        self.spans.is_some()
+    }
+
+    /// Record a span measured elsewhere
+    pub fn record(&self, name: &str, duration: Duration) {
+        if let Some(spans) = &self.spans {
+            spans.lock().unwrap().push((name.to_string(), duration));
+        }
+    }
+
+    /// Run the future built by `f` and record how long it took as `name`
+    pub async fn time<F, Fut, T>(&self, name: &str, f: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        if !self.is_enabled() {
+            return f().await;
+        }
+        let started = Instant::now();
+        let output = f().await;
+        self.record(name, started.elapsed());
+        output
+    }
+
+    /// Spans recorded so far, in the order they finished
+    pub fn spans(&self) -> Vec<Span> {
+        self.spans
+            .as_ref()
+            .map(|spans| spans.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// `Server-Timing` value such as `db;dur=12.3, render;dur=0.8`
+    /// Durations are in milliseconds; characters not allowed in a token are
+    /// replaced with `_` in names. `None` when nothing was recorded.
+    pub fn header_value(&self) -> Option<String> {
+        let spans = self.spans();
+        if spans.is_empty() {
+            return None;
+        }
+        let metrics: Vec<String> = spans
+            .iter()
+            .map(|(name, duration)| {
+                format!("{};dur={:.1}", token(name), duration.as_secs_f64() * 1000.0)
+            })
+            .collect();
+        Some(metrics.join(", "))
+    }
+}
+
+/// `name` with every character outside the HTTP token set replaced
+fn token(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "_".to_string()
+    } else {
+        name
+    }
+}
@@ -1,5 +1,7 @@
 pub mod app;
 pub mod errors;
+#[cfg(feature = "serde")]
+pub mod extract;
 pub mod handler;
 pub mod http;
 pub mod middleware;
@@ -11,7 +13,14 @@ pub mod types;
 pub mod prelude {
     pub use crate::app::expresso::Expresso;
     pub use crate::handler::{IntoHandler, IntoHandlers};
-    pub use crate::http::{request::Request, response::Response};
+    pub use crate::http::{
+        cache_control::CacheControl,
+        cookie::{CookieOptions, SameSite},
+        request::Request,
+        response::{IntoResponse, Response},
+        status::StatusCode,
+    };
+    pub use crate::middleware::NextExt;
     pub use crate::router::Method;
     pub use crate::types::{Handler, Next};
 }
@@ -59,9 +59,10 @@ async fn main() -> tokio::io::Result<()> {
             match req.body() {
                 Some(body) => {
                     println!("📨 Received: {}", body);
-                    res.status(201)
-                        .set_header("Content-Type", "application/json")
-                        .json(&format!(r#"{{"message":"Data received","data":{}}}"#, body))
+                    res.status(201).json_object(&[
+                        ("message", "Data received".into()),
+                        ("data", body.as_str().into()),
+                    ])
                 }
                 None => res.status(400).send("Bad Request: No body"),
             }
@@ -86,12 +87,54 @@ async fn main() -> tokio::io::Result<()> {
     )
     .await;
 
-    let port = 3000;
-    app.listen(port, move || {
+    // Server-sent events, one per second, that end cleanly on shutdown
+    app.get(
+        "/events",
+        (|req: Request, res: Response, _next: Next| async move {
+            let shutdown = req.shutdown_signal();
+            let (res, body) = res
+                .status(200)
+                .set_header("Content-Type", "text/event-stream")
+                .set_header("Cache-Control", "no-cache")
+                .chunked();
+
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+                for id in 0.. {
+                    let event = tokio::select! {
+                        _ = shutdown.cancelled() => {
+                            // Dropping the sender afterwards ends the body
+                            let _ = body.send("event: shutdown\ndata: bye\n\n").await;
+                            return;
+                        }
+                        _ = interval.tick() => format!("id: {}\ndata: tick\n\n", id),
+                    };
+                    // The client went away
+                    if body.send(event).await.is_err() {
+                        return;
+                    }
+                }
+            });
+            res
+        },),
+    )
+    .await;
+
+    // Stop the server on Ctrl+C
+    let shutdown = app.shutdown_signal();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            shutdown.cancel();
+        }
+    });
+
+    // The callback receives the bound address, so port 0 would print the real port
+    app.listen_with_addr("127.0.0.1:3000", move |addr| {
+        let url = format!("http://{}", addr);
         println!("\n╔════════════════════════════════════════╗");
         println!("║  🚀 Expresso Server Started!          ║");
         println!("╠════════════════════════════════════════╣");
-        println!("║  📍 Address: http://127.0.0.1:{}     ║", port);
+        println!("║  📍 Address: {:<26}║", url);
         println!("╠════════════════════════════════════════╣");
         println!("║  Available Routes:                     ║");
         println!("║  • GET    /hello                       ║");
@@ -99,9 +142,10 @@ async fn main() -> tokio::io::Result<()> {
         println!("║  • POST   /submit                      ║");
         println!("║  • PUT    /update                      ║");
         println!("║  • DELETE /delete                      ║");
+        println!("║  • GET    /events                      ║");
         println!("╠════════════════════════════════════════╣");
         println!("║  💡 Test with curl:                    ║");
-        println!("║  curl http://127.0.0.1:{}       ║", port);
+        println!("║  curl {:<33}║", url);
         println!("╚════════════════════════════════════════╝\n");
     })
     .await
@@ -1,14 +1,18 @@
 /// Middleware management and execution
 use crate::{
+    handler::IntoHandler,
     http::{request::Request, response::Response},
-    types::{Handler, Next},
+    types::{BoxFuture, Handler, Next},
 };
-use std::sync::Arc;
+use std::{future::Future, ops::ControlFlow, sync::Arc};
 use tokio::sync::RwLock;
 
+/// Name reported for middleware registered without one
+pub const ANONYMOUS: &str = "<anonymous>";
+
 /// Middleware manager stores and executes middleware chain
 pub struct MiddlewareManager {
-    middlewares: RwLock<Vec<Handler>>,
+    middlewares: RwLock<Vec<(String, Handler)>>,
 }
 
 impl MiddlewareManager {
@@ -21,12 +25,30 @@ impl MiddlewareManager {
 
     /// Add a middleware to the stack
     pub async fn add(&self, middleware: Handler) {
+        self.add_named(ANONYMOUS, middleware).await;
+    }
+
+    /// Add a middleware to the stack under `name`, as listed by `names`
+    pub async fn add_named(&self, name: &str, middleware: Handler) {
         let mut middlewares = self.middlewares.write().await;
-        middlewares.push(middleware);
+        middlewares.push((name.to_string(), middleware));
+    }
+
+    /// Add a middleware without locking, for setup before the manager is shared
+    pub fn push(&mut self, middleware: Handler) {
+        self.push_named(ANONYMOUS, middleware);
+    }
+
+    /// Add a named middleware without locking, see `push`
+    pub fn push_named(&mut self, name: &str, middleware: Handler) {
+        self.middlewares
+            .get_mut()
+            .push((name.to_string(), middleware));
     }
 
     /// Build a middleware chain that wraps the final handler
-    /// Middlewares execute in the order they were added
+    /// Middlewares execute in the order they were added, and the chain's
+    /// `next` is passed through to the final handler
     pub async fn build_chain(&self, final_handler: Handler) -> Handler {
         let middlewares = self.middlewares.read().await.clone();
 
@@ -35,8 +57,8 @@ impl MiddlewareManager {
         middlewares
             .into_iter()
             .rev()
-            .fold(final_handler, |next_handler, mw| {
-                Arc::new(move |req: Request, res: Response, _final_next: Next| {
+            .fold(final_handler, |next_handler, (_, mw)| {
+                Arc::new(move |req: Request, res: Response, final_next: Next| {
                     let next_handler = next_handler.clone();
                     let mw = mw.clone();
 
@@ -52,18 +74,10 @@ impl MiddlewareManager {
                                 let next_handler = next_handler.clone();
                                 let req_clone = req_clone.clone();
                                 let res_clone = res_clone.clone();
+                                let final_next = final_next.clone();
 
                                 Box::pin(async move {
-                                    next_handler(
-                                        req_clone,
-                                        res_clone,
-                                        Arc::new(|| {
-                                            Box::pin(async {
-                                                Response::new().status(500).send("Internal Error")
-                                            })
-                                        }),
-                                    )
-                                    .await
+                                    next_handler(req_clone, res_clone, final_next).await
                                 })
                             }),
                         )
@@ -78,6 +92,13 @@ impl MiddlewareManager {
         let middlewares = self.middlewares.read().await;
         middlewares.len()
     }
+
+    /// Names of the registered middlewares in execution order
+    /// Anonymous ones are listed as `"<anonymous>"`
+    pub async fn names(&self) -> Vec<String> {
+        let middlewares = self.middlewares.read().await;
+        middlewares.iter().map(|(name, _)| name.clone()).collect()
+    }
 }
 
 impl Default for MiddlewareManager {
@@ -85,3 +106,184 @@ impl Default for MiddlewareManager {
         Self::new()
     }
 }
+
+/// Combinators for middleware that post-process the rest of the chain
+///
+/// `next.map(f)` runs the chain and passes its response through `f`, and
+/// `then` does the same with an async `f`. Both return the `BoxFuture` a
+/// middleware returns, and `next()` stays callable directly as before.
+///
+/// ```ignore
+/// app.use_middleware(|_req, _res, next: Next| {
+///     next.map(|res| res.set_header("X-Frame-Options", "DENY"))
+/// })
+/// .await;
+/// ```
+pub trait NextExt {
+    /// Run the rest of the chain, then transform its response
+    fn map<F>(&self, transform: F) -> BoxFuture
+    where
+        F: FnOnce(Response) -> Response + Send + 'static;
+
+    /// Run the rest of the chain, then transform its response asynchronously
+    fn then<F, Fut>(&self, transform: F) -> BoxFuture
+    where
+        F: FnOnce(Response) -> Fut + Send + 'static,
+        Fut: Future<Output = Response> + Send + 'static;
+}
+
+impl NextExt for Next {
+    fn map<F>(&self, transform: F) -> BoxFuture
+    where
+        F: FnOnce(Response) -> Response + Send + 'static,
+    {
+        let rest = self();
+        Box::pin(async move { transform(rest.await) })
+    }
+
+    fn then<F, Fut>(&self, transform: F) -> BoxFuture
+    where
+        F: FnOnce(Response) -> Fut + Send + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        let rest = self();
+        Box::pin(async move { transform(rest.await).await })
+    }
+}
+
+/// Run `middleware` only for requests matching `predicate`
+/// Other requests go straight to `next()`
+///
+/// ```ignore
+/// app.use_middleware(when(|req| req.path().starts_with("/api/"), auth)).await;
+/// ```
+pub fn when<P, M>(
+    predicate: P,
+    middleware: M,
+) -> impl Fn(Request, Response, Next) -> BoxFuture + Send + Sync + 'static
+where
+    P: Fn(&Request) -> bool + Send + Sync + 'static,
+    M: IntoHandler,
+{
+    let middleware = middleware.into_handler();
+    move |req: Request, res: Response, next: Next| {
+        if predicate(&req) {
+            middleware(req, res, next)
+        } else {
+            Box::pin(async move { next().await })
+        }
+    }
+}
+
+/// Run `middleware` only for paths at or below `prefix`
+///
+/// The prefix matches whole segments: `/admin` covers `/admin` and
+/// `/admin/users` but not `/administrators`. A trailing `/` is ignored.
+///
+/// ```ignore
+/// app.use_middleware(for_prefix("/admin", auth)).await;
+/// ```
+pub fn for_prefix<M>(
+    prefix: &str,
+    middleware: M,
+) -> impl Fn(Request, Response, Next) -> BoxFuture + Send + Sync + 'static
+where
+    M: IntoHandler,
+{
+    let prefix = prefix.trim_end_matches('/').to_string();
+    when(
+        move |req| {
+            req.path()
+                .strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        },
+        middleware,
+    )
+}
+
+/// Middleware whose return type states whether the chain continues
+///
+/// `ControlFlow::Break(response)` short-circuits with that response and
+/// `ControlFlow::Continue(())` runs the rest of the chain. Plain middleware
+/// that returns without calling `next()` still works; this only makes the
+/// intent explicit.
+///
+/// ```ignore
+/// app.use_middleware(guard(|req, res| async move {
+///     match req.header("Authorization") {
+///         Some(_) => ControlFlow::Continue(()),
+///         None => ControlFlow::Break(res.status(401).send("Unauthorized")),
+///     }
+/// }))
+/// .await;
+/// ```
+pub fn guard<F, Fut>(
+    check: F,
+) -> impl Fn(Request, Response, Next) -> BoxFuture + Send + Sync + 'static
+where
+    F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ControlFlow<Response>> + Send + 'static,
+{
+    move |req: Request, res: Response, next: Next| {
+        let decision = check(req, res);
+        Box::pin(async move {
+            match decision.await {
+                ControlFlow::Break(res) => res,
+                ControlFlow::Continue(()) => next().await,
+            }
+        })
+    }
+}
+
+/// Run `handler` only when query parameter `key` equals `value`
+///
+/// Responds 400 when the parameter is absent and 404 when it has another
+/// value. To dispatch on several values within one route, match on
+/// `req.query(key)` inside a single handler instead.
+///
+/// ```ignore
+/// app.get("/posts", (when_query("action", "edit", edit_post),)).await;
+/// ```
+pub fn when_query<H>(
+    key: &'static str,
+    value: &'static str,
+    handler: H,
+) -> impl Fn(Request, Response, Next) -> BoxFuture + Send + Sync + 'static
+where
+    H: IntoHandler,
+{
+    let handler = handler.into_handler();
+    move |req: Request, res: Response, next: Next| match req.query(key) {
+        Some(found) if found == value => handler(req, res, next),
+        Some(_) => Box::pin(async move { res.status(404).send("Not Found") }),
+        None => Box::pin(async move {
+            res.status(400)
+                .send(&format!("Bad Request: missing query parameter `{}`", key))
+        }),
+    }
+}
+
+/// Set `Content-Type` on responses that have a body but no explicit type
+///
+/// Use it as the first handler of a route, or through
+/// `RouteGroup::default_content_type` for a whole group. A type set by the
+/// handler (including `json`, `html` and `send_file`) always wins.
+///
+/// ```ignore
+/// app.get("/docs", (default_content_type("text/html"), docs)).await;
+/// ```
+pub fn default_content_type(
+    content_type: &'static str,
+) -> impl Fn(Request, Response, Next) -> BoxFuture + Send + Sync + 'static {
+    move |_req: Request, _res: Response, next: Next| {
+        Box::pin(async move {
+            let res = next().await;
+            let has_body = res.body.is_some() || res.file.is_some() || res.stream.is_some();
+            if has_body && res.header("Content-Type").is_none() {
+                res.content_type(content_type)
+            } else {
+                res
+            }
+        })
+    }
+}
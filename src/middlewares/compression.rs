@@ -0,0 +1,167 @@
+/// Gzip compression of response bodies
+use crate::{
+    http::{qlist, request::Request, response::Response},
+    types::Next,
+};
+use flate2::{write::GzEncoder, Compression};
+use std::{io::Write, sync::Arc};
+
+/// Gzip response bodies for clients that accept it
+///
+/// ```ignore
+/// app.use_middleware(compression::gzip()).await;
+/// ```
+pub fn gzip() -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + 'static {
+    GzipConfig::new().build()
+}
+
+/// Gzip compression configuration
+///
+/// After `next()` returns, in-memory bodies of at least `min_size` bytes
+/// are compressed when the request's `Accept-Encoding` allows `gzip`, and the
+/// response gets `Content-Encoding: gzip` and `Vary: Accept-Encoding`. The
+/// body is left as it is when the response already has a `Content-Encoding`,
+/// is a partial (206) response, has a media type that is compressed already
+/// (images, audio, video, archives), or would not get smaller. File and
+/// streamed bodies are never compressed. A strong `ETag` is weakened, since
+/// the bytes no longer match the uncompressed representation.
+pub struct GzipConfig {
+    /// Smallest body worth compressing, in bytes
+    pub min_size: usize,
+    /// Compression level from 0 (none) to 9 (smallest)
+    pub level: u32,
+}
+
+impl Default for GzipConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 1,
+            level: 6,
+        }
+    }
+}
+
+impl GzipConfig {
+    /// Create a configuration compressing every non-empty body at level 6
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leave bodies shorter than `bytes` uncompressed
+    pub fn min_size(mut self, bytes: usize) -> Self {
+        self.min_size = bytes.max(1);
+        self
+    }
+
+    /// Set the compression level, clamped to 9
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Build the middleware function
+    pub fn build(
+        self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + 'static {
+        let config = Arc::new(self);
+        move |req: Request, _res: Response, next: Next| {
+            let config = config.clone();
+            let accepted = req
+                .header("Accept-Encoding")
+                .is_some_and(|header| accepts_gzip(header));
+            Box::pin(async move {
+                let res = next().await;
+                if !compressible(&res) {
+                    return res;
+                }
+                let res = res.append_header("Vary", "Accept-Encoding");
+                if accepted {
+                    config.compress(res)
+                } else {
+                    res
+                }
+            })
+        }
+    }
+
+    fn compress(&self, mut res: Response) -> Response {
+        let Some(body) = res.body.as_deref() else {
+            return res;
+        };
+        if body.len() < self.min_size {
+            return res;
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::new(self.level));
+        let compressed = match encoder.write_all(body).and_then(|_| encoder.finish()) {
+            Ok(compressed) if compressed.len() < body.len() => compressed,
+            _ => return res,
+        };
+
+        // Keep the type the uncompressed body would have been sent with
+        if let Some(content_type) = res.implied_content_type() {
+            res = res.content_type(&content_type);
+        }
+        if let Some(tag) = res.header("ETag").filter(|tag| !tag.starts_with("W/")) {
+            let weak = format!("W/{}", tag);
+            res = res.set_header("ETag", &weak);
+        }
+        if res.header("Content-Length").is_some() {
+            res = res.set_header("Content-Length", &compressed.len().to_string());
+        }
+        res.set_header("Content-Encoding", "gzip")
+            .send_bytes(compressed)
+    }
+}
+
+/// Whether `Accept-Encoding` gives `gzip`, directly or through `*`, a nonzero weight
+fn accepts_gzip(header: &str) -> bool {
+    let encodings = qlist::parse(header);
+    let weight = |name: &str| {
+        encodings
+            .iter()
+            .find(|(encoding, _)| encoding.eq_ignore_ascii_case(name))
+            .map(|(_, quality)| *quality)
+    };
+    weight("gzip")
+        .or_else(|| weight("x-gzip"))
+        .or_else(|| weight("*"))
+        .is_some_and(|quality| quality > 0.0)
+}
+
+/// Whether the body could be compressed, whatever the client accepts
+fn compressible(res: &Response) -> bool {
+    if res.status_code == 206 || res.header("Content-Encoding").is_some() {
+        return false;
+    }
+    if res.body.as_ref().is_none_or(|body| body.is_empty()) {
+        return false;
+    }
+    let essence = res
+        .header("Content-Type")
+        .and_then(|value| value.split(';').next())
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let precompressed = (essence.starts_with("image/") && essence != "image/svg+xml")
+        || essence.starts_with("audio/")
+        || essence.starts_with("video/")
+        || matches!(
+            essence.as_str(),
+            "application/gzip" | "application/zip" | "application/x-7z-compressed" | "font/woff2"
+        );
+    !precompressed
+}
@@ -0,0 +1,95 @@
+/// Bounding how many requests run handler logic at once
+use crate::{
+    http::{request::Request, response::Response},
+    types::Next,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Semaphore;
+
+/// Let at most `max` requests past this middleware at a time, rejecting the rest with 503
+pub fn concurrency_limit(
+    max: usize,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + 'static {
+    ConcurrencyConfig::new(max).build()
+}
+
+/// Custom concurrency limit configuration
+///
+/// Unlike a connection limit, this bounds the requests running the rest of
+/// the chain, e.g. to protect a database behind the handlers. A request holds
+/// its permit until the rest of the chain returns, panics or is cancelled. Over the
+/// limit it waits up to `queue_timeout` for a permit (rejecting immediately
+/// when `None`), then gets `503 Service Unavailable` with `Retry-After`.
+/// Middleware registered before this one runs without a permit.
+pub struct ConcurrencyConfig {
+    /// Requests allowed past the middleware at once
+    pub max: usize,
+    /// How long an over-limit request waits for a permit
+    pub queue_timeout: Option<Duration>,
+    /// Seconds sent in `Retry-After` with the 503
+    pub retry_after: u64,
+}
+
+impl ConcurrencyConfig {
+    /// Create a configuration allowing `max` concurrent requests, clamped to at least one
+    pub fn new(max: usize) -> Self {
+        Self {
+            max: max.max(1),
+            queue_timeout: None,
+            retry_after: 1,
+        }
+    }
+
+    /// Queue over-limit requests for up to `timeout` instead of rejecting them
+    pub fn queue(mut self, timeout: Duration) -> Self {
+        self.queue_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the `Retry-After` delay in seconds
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = seconds;
+        self
+    }
+
+    /// Build the middleware function
+    pub fn build(
+        self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + 'static {
+        let permits = Arc::new(Semaphore::new(self.max.max(1)));
+        let config = Arc::new(self);
+        move |_req: Request, _res: Response, next: Next| {
+            let permits = permits.clone();
+            let config = config.clone();
+            Box::pin(async move {
+                let permit = match config.queue_timeout {
+                    None => permits.try_acquire_owned().ok(),
+                    Some(timeout) => tokio::time::timeout(timeout, permits.acquire_owned())
+                        .await
+                        .ok()
+                        .and_then(Result::ok),
+                };
+                let Some(_permit) = permit else {
+                    return Response::from_status(503)
+                        .set_header("Retry-After", &config.retry_after.to_string());
+                };
+                // Dropped when this future completes, unwinds or is dropped
+                next().await
+            })
+        }
+    }
+}
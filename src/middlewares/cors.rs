@@ -1,19 +1,23 @@
 use crate::{
     http::{request::Request, response::Response},
+    middleware::NextExt,
     types::Next,
 };
+use std::sync::Arc;
 
 pub async fn cors(_req: Request, _res: Response, next: Next) -> Response {
-    let res = next().await;
-    res.set_header("Access-Control-Allow-Origin", "*")
-        .set_header(
-            "Access-Control-Allow-Methods",
-            "GET, POST, PUT, DELETE, PATCH, OPTIONS",
-        )
-        .set_header(
-            "Access-Control-Allow-Headers",
-            "Content-Type, Authorization",
-        )
+    next.map(|res| {
+        res.set_header("Access-Control-Allow-Origin", "*")
+            .set_header(
+                "Access-Control-Allow-Methods",
+                "GET, POST, PUT, DELETE, PATCH, OPTIONS",
+            )
+            .set_header(
+                "Access-Control-Allow-Headers",
+                "Content-Type, Authorization",
+            )
+    })
+    .await
 }
 
 pub fn with_origin(
@@ -27,8 +31,7 @@ pub fn with_origin(
        + Sync
        + 'static {
     move |_req: Request, _res: Response, next: Next| {
-        Box::pin(async move {
-            let res = next().await;
+        next.map(move |res| {
             res.set_header("Access-Control-Allow-Origin", origin)
                 .set_header(
                     "Access-Control-Allow-Methods",
@@ -43,10 +46,33 @@ pub fn with_origin(
 }
 
 /// Custom CORS configuration
+///
+/// `Access-Control-Allow-Origin` holds a single origin, so the request's
+/// `Origin` is echoed back when it is in `origins`, with `Vary: Origin`.
+/// Requests from other origins get no CORS headers, which browsers treat as
+/// a refusal. An `origins` list containing `"*"` allows every origin.
+///
+/// With `credentials` set, `Access-Control-Allow-Credentials: true` is sent.
+/// Browsers refuse credentials with a `*` origin, so a wildcard list then
+/// echoes whichever origin made the request.
+///
+/// With `preflight` set, `OPTIONS` requests are answered with 204 and the
+/// `Access-Control-Allow-*` headers without reaching `next`, so browsers'
+/// preflight requests succeed even when no route handles `OPTIONS`.
+/// `max_age` is sent on `OPTIONS` responses only, since it applies to the
+/// preflight.
 pub struct CorsConfig {
     pub origins: Vec<String>,
     pub methods: Vec<String>,
     pub headers: Vec<String>,
+    /// Response headers scripts may read, beyond the safelisted ones
+    pub expose_headers: Vec<String>,
+    /// Allow cookies and `Authorization` on cross-origin requests
+    pub credentials: bool,
+    /// Seconds browsers may cache a preflight answer
+    pub max_age: Option<u64>,
+    /// Answer `OPTIONS` requests here instead of passing them on
+    pub preflight: bool,
 }
 
 impl Default for CorsConfig {
@@ -62,6 +88,10 @@ impl Default for CorsConfig {
                 "OPTIONS".to_string(),
             ],
             headers: vec!["Content-Type".to_string(), "Authorization".to_string()],
+            expose_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+            preflight: false,
         }
     }
 }
@@ -90,6 +120,30 @@ impl CorsConfig {
         self
     }
 
+    /// Set the response headers exposed to scripts
+    pub fn expose_headers(mut self, headers: Vec<String>) -> Self {
+        self.expose_headers = headers;
+        self
+    }
+
+    /// Allow credentials, echoing the request's origin instead of `*`
+    pub fn credentials(mut self, enabled: bool) -> Self {
+        self.credentials = enabled;
+        self
+    }
+
+    /// Let browsers cache preflight answers for `seconds`
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Answer `OPTIONS` preflight requests with 204 instead of calling `next`
+    pub fn preflight(mut self, enabled: bool) -> Self {
+        self.preflight = enabled;
+        self
+    }
+
     /// Build the middleware function
     pub fn build(
         self,
@@ -101,21 +155,63 @@ impl CorsConfig {
            + Send
            + Sync
            + 'static {
-        let origins = self.origins.join(", ");
-        let methods = self.methods.join(", ");
-        let headers = self.headers.join(", ");
+        let config = Arc::new(self);
 
-        move |_req: Request, _res: Response, next: Next| {
-            let origins = origins.clone();
-            let methods = methods.clone();
-            let headers = headers.clone();
+        move |req: Request, _res: Response, next: Next| {
+            let config = config.clone();
 
             Box::pin(async move {
-                let res = next().await;
-                res.set_header("Access-Control-Allow-Origin", &origins)
-                    .set_header("Access-Control-Allow-Methods", &methods)
-                    .set_header("Access-Control-Allow-Headers", &headers)
+                let options = req.method() == "OPTIONS";
+                let mut res = if config.preflight && options {
+                    Response::new().status(204)
+                } else {
+                    next().await
+                };
+                if config.echoes_origin() {
+                    res = res.append_header("Vary", "Origin");
+                }
+                let Some(origin) = config.allowed_origin(&req) else {
+                    return res;
+                };
+                res = res
+                    .set_header("Access-Control-Allow-Origin", &origin)
+                    .set_header("Access-Control-Allow-Methods", &config.methods.join(", "))
+                    .set_header("Access-Control-Allow-Headers", &config.headers.join(", "));
+                if config.credentials {
+                    res = res.set_header("Access-Control-Allow-Credentials", "true");
+                }
+                if !config.expose_headers.is_empty() {
+                    res = res.set_header(
+                        "Access-Control-Expose-Headers",
+                        &config.expose_headers.join(", "),
+                    );
+                }
+                match config.max_age {
+                    Some(seconds) if options => {
+                        res.set_header("Access-Control-Max-Age", &seconds.to_string())
+                    }
+                    _ => res,
+                }
             })
         }
     }
+
+    fn any_origin(&self) -> bool {
+        self.origins.iter().any(|origin| origin == "*")
+    }
+
+    /// Whether the allowed origin depends on the request's `Origin`
+    fn echoes_origin(&self) -> bool {
+        self.credentials || !self.any_origin()
+    }
+
+    /// The `Access-Control-Allow-Origin` value for `req`, if it is allowed
+    fn allowed_origin(&self, req: &Request) -> Option<String> {
+        if !self.echoes_origin() {
+            return Some("*".to_string());
+        }
+        req.header("Origin")
+            .filter(|origin| self.any_origin() || self.origins.contains(origin))
+            .cloned()
+    }
 }
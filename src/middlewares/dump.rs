@@ -0,0 +1,161 @@
+/// Full request/response dumps for local debugging
+use crate::{
+    http::{request::Request, response::Response},
+    middlewares::logger::is_text,
+    types::Next,
+};
+use std::{fmt::Write, sync::Arc, time::Instant};
+
+/// Dump every request and response with the default `DumpConfig`
+pub fn dump() -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + 'static {
+    DumpConfig::new().build()
+}
+
+/// Dump configuration, for development only
+///
+/// Prints the method, target, every header and a body preview of each
+/// request, then the status, every header and a body preview of its
+/// response, between delimiter lines. Nothing is redacted, so never enable it
+/// where real credentials pass through; building it in a release build prints a
+/// warning. Bodies are cut after `preview_len` characters, and bodies that are
+/// not text (by Content-Type, invalid UTF-8 or control characters) are shown
+/// by size only. Disabled dumps pass requests straight through.
+pub struct DumpConfig {
+    /// Whether requests are dumped at all
+    pub enabled: bool,
+    /// Characters shown per body
+    pub preview_len: usize,
+}
+
+impl Default for DumpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            preview_len: 2048,
+        }
+    }
+}
+
+impl DumpConfig {
+    /// Create a new dump configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn dumping on or off, e.g. with `cfg!(debug_assertions)`
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Set the number of characters shown per body
+    pub fn preview_len(mut self, preview_len: usize) -> Self {
+        self.preview_len = preview_len;
+        self
+    }
+
+    /// Build the middleware function
+    pub fn build(
+        self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + 'static {
+        if self.enabled && !cfg!(debug_assertions) {
+            println!(
+                "⚠️  [Dump] Request dumping is enabled in a release build: \
+                 every header and body, credentials included, is printed"
+            );
+        }
+        let config = Arc::new(self);
+        move |req: Request, _res: Response, next: Next| {
+            let config = config.clone();
+            Box::pin(async move {
+                if !config.enabled {
+                    return next().await;
+                }
+                println!("{}", config.request(&req));
+                let start = Instant::now();
+                let res = next().await;
+                println!("{}", config.response(&req, &res, start));
+                res
+            })
+        }
+    }
+
+    fn request(&self, req: &Request) -> String {
+        let target = match &req.query_string {
+            Some(query) => format!("{}?{}", req.path(), query),
+            None => req.path().to_string(),
+        };
+        let mut out = format!("━━━━━━━━ ▶ REQUEST {} {} ━━━━━━━━\n", req.method(), target);
+        let _ = writeln!(out, "{} {} {}", req.method(), target, req.version);
+        write_headers(&mut out, req.headers.iter());
+        out.push('\n');
+        out.push_str(&self.preview(req.body_bytes(), req.header("Content-Type")));
+        out
+    }
+
+    fn response(&self, req: &Request, res: &Response, start: Instant) -> String {
+        let mut out = format!(
+            "━━━━━━━━ ◀ RESPONSE {} {} ({:.2}ms) ━━━━━━━━\n",
+            req.method(),
+            req.path(),
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+        let _ = writeln!(out, "HTTP/1.1 {} {}", res.status_code, res.reason_text());
+        write_headers(&mut out, res.headers.iter());
+        out.push('\n');
+        let body = if let Some(file) = &res.file {
+            format!("<file {}, {} bytes>", file.path.display(), file.len)
+        } else if res.stream.is_some() {
+            "<streamed body>".to_string()
+        } else {
+            self.preview(res.body.as_deref(), res.header("Content-Type"))
+        };
+        out.push_str(&body);
+        out.push_str("\n━━━━━━━━ END ━━━━━━━━");
+        out
+    }
+
+    /// Truncated text, or a size note for empty and binary bodies
+    fn preview(&self, body: Option<&[u8]>, content_type: Option<&String>) -> String {
+        let Some(body) = body.filter(|body| !body.is_empty()) else {
+            return "(empty body)".to_string();
+        };
+        let text = std::str::from_utf8(body).ok().filter(|text| {
+            is_text(content_type, text)
+                && !text
+                    .chars()
+                    .any(|c| c.is_control() && !matches!(c, '\t' | '\r' | '\n'))
+        });
+        let Some(text) = text else {
+            let kind = content_type.map_or("binary", |ct| ct.as_str());
+            return format!("<{} bytes, {}>", body.len(), kind);
+        };
+        match text.char_indices().nth(self.preview_len) {
+            Some((cut, _)) => format!("{}… ({} more bytes)", &text[..cut], text.len() - cut),
+            None => text.to_string(),
+        }
+    }
+}
+
+/// Headers one per line, sorted by name so dumps compare easily
+fn write_headers<'a>(out: &mut String, headers: impl Iterator<Item = (&'a str, &'a String)>) {
+    let mut headers: Vec<_> = headers.collect();
+    headers.sort();
+    for (name, value) in headers {
+        let _ = writeln!(out, "{}: {}", name, value);
+    }
+}
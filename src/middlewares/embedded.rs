@@ -0,0 +1,166 @@
+/// Serving assets compiled into the binary
+use crate::{
+    http::{
+        cache_control::CacheControl,
+        etag, mime,
+        range::{self, RangeOutcome},
+        request::Request,
+        response::Response,
+    },
+    types::Next,
+};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+/// Serve `assets` under `mount` straight from memory
+///
+/// Each asset is `(path, bytes, content_type)`, with the path relative to
+/// the mount; an empty content type is guessed from the extension. GET and
+/// HEAD requests for a known path get the bytes with a content-hash `ETag`
+/// (`If-None-Match` is answered with 304) and `Range` support; a directory
+/// path serves its `index.html`. Everything else falls through to `next`.
+///
+/// Assets whose file name carries a content hash (`app.3f9a2c1b.js`,
+/// `logo-8d41f0e2.png`) are cached for a year as immutable; all others,
+/// including `index.html`, are sent with `no-cache` so clients revalidate
+/// them with the `ETag` on every use.
+///
+/// ```ignore
+/// app.use_middleware(embedded::serve_embedded("/static", &[
+///     ("app.js", include_bytes!("../dist/app.js"), ""),
+///     ("logo.png", include_bytes!("../dist/logo.png"), "image/png"),
+/// ]))
+/// .await;
+/// ```
+pub fn serve_embedded(
+    mount: &str,
+    assets: &[(&str, &'static [u8], &str)],
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + 'static {
+    let bundle = Arc::new(Bundle::new(mount, assets));
+    move |req: Request, _res: Response, next: Next| {
+        let bundle = bundle.clone();
+        Box::pin(async move {
+            match bundle.find(&req) {
+                Some(asset) => asset.respond(&req),
+                None => next().await,
+            }
+        })
+    }
+}
+
+/// An embedded file with its precomputed headers
+struct Asset {
+    bytes: &'static [u8],
+    content_type: String,
+    etag: String,
+    cache_control: CacheControl,
+}
+
+/// Assets keyed by their path below the mount
+struct Bundle {
+    mount: String,
+    assets: HashMap<String, Asset>,
+}
+
+impl Bundle {
+    fn new(mount: &str, assets: &[(&str, &'static [u8], &str)]) -> Self {
+        let assets = assets
+            .iter()
+            .map(|&(path, bytes, content_type)| {
+                let path = path.trim_start_matches('/');
+                let content_type = if content_type.is_empty() {
+                    mime::from_path(Path::new(path)).to_string()
+                } else {
+                    content_type.to_string()
+                };
+                let cache_control = if is_hashed(path) {
+                    CacheControl::immutable_asset()
+                } else {
+                    CacheControl::new().no_cache()
+                };
+                let asset = Asset {
+                    bytes,
+                    content_type,
+                    etag: etag::from_content(bytes),
+                    cache_control,
+                };
+                (path.to_string(), asset)
+            })
+            .collect();
+        Self {
+            mount: mount.trim_end_matches('/').to_string(),
+            assets,
+        }
+    }
+
+    fn find(&self, req: &Request) -> Option<&Asset> {
+        if !matches!(req.method(), "GET" | "HEAD") {
+            return None;
+        }
+        let rest = req.path().strip_prefix(self.mount.as_str())?;
+        if !(rest.is_empty() || rest.starts_with('/')) {
+            return None;
+        }
+        let path = rest.trim_start_matches('/');
+        if path.is_empty() || path.ends_with('/') {
+            return self.assets.get(&format!("{}index.html", path));
+        }
+        self.assets.get(path)
+    }
+}
+
+impl Asset {
+    fn respond(&self, req: &Request) -> Response {
+        let res = Response::new()
+            .cache_control(self.cache_control.clone())
+            .set_header("ETag", &self.etag)
+            .set_header("Accept-Ranges", "bytes");
+        if req
+            .header("If-None-Match")
+            .is_some_and(|header| etag::none_match(header, &self.etag))
+        {
+            return res.status(304);
+        }
+
+        let len = self.bytes.len() as u64;
+        // A stale `If-Range` validator means the client's partial copy is
+        // outdated, so it gets the whole asset
+        let outcome = match req.header("Range") {
+            Some(_) if req.header("If-Range").is_some_and(|v| *v != self.etag) => {
+                RangeOutcome::Full
+            }
+            Some(header) => range::resolve(header, len),
+            None => RangeOutcome::Full,
+        };
+        let res = res.content_type(&self.content_type);
+        match outcome {
+            RangeOutcome::Full => res.status(200).send_static(self.bytes),
+            RangeOutcome::Partial(range) => res
+                .status(206)
+                .set_header("Content-Range", &range.content_range(len))
+                .send_static(&self.bytes[range.start as usize..=range.end as usize]),
+            RangeOutcome::Unsatisfiable => res
+                .status(416)
+                .remove_header("Content-Type")
+                .set_header("Content-Range", &format!("bytes */{}", len)),
+        }
+    }
+}
+
+/// Whether a file name carries a content hash: a dot-, dash- or
+/// underscore-separated part of at least 8 hex digits, including a digit
+pub(crate) fn is_hashed(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    stem.split(['.', '-', '_']).any(|part| {
+        part.len() >= 8
+            && part.chars().all(|c| c.is_ascii_hexdigit())
+            && part.chars().any(|c| c.is_ascii_digit())
+    })
+}
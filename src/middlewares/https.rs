@@ -0,0 +1,98 @@
+/// HTTPS enforcement middleware
+use crate::{
+    http::{request::Request, response::Response},
+    types::Next,
+};
+
+/// Redirect plain-HTTP GET/HEAD requests to HTTPS and reject other methods
+pub async fn require_https(req: Request, res: Response, next: Next) -> Response {
+    enforce(&HttpsConfig::default(), req, res, next).await
+}
+
+/// What to do with requests that did not arrive over HTTPS
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HttpsMode {
+    /// 301 to the `https://` URL for GET/HEAD, 403 for other methods
+    Redirect,
+    /// 403 for every method
+    Reject,
+}
+
+/// Custom HTTPS enforcement configuration
+pub struct HttpsConfig {
+    pub mode: HttpsMode,
+    /// Treat `X-Forwarded-Proto: https` as secure (for apps behind a proxy)
+    pub trust_forwarded_proto: bool,
+}
+
+impl Default for HttpsConfig {
+    fn default() -> Self {
+        Self {
+            mode: HttpsMode::Redirect,
+            trust_forwarded_proto: true,
+        }
+    }
+}
+
+impl HttpsConfig {
+    /// Create a new HTTPS configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how insecure requests are handled
+    pub fn mode(mut self, mode: HttpsMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set whether `X-Forwarded-Proto` is trusted
+    pub fn trust_forwarded_proto(mut self, trust: bool) -> Self {
+        self.trust_forwarded_proto = trust;
+        self
+    }
+
+    /// Build the middleware function
+    pub fn build(
+        self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + 'static {
+        let config = std::sync::Arc::new(self);
+        move |req: Request, res: Response, next: Next| {
+            let config = config.clone();
+            Box::pin(async move { enforce(&config, req, res, next).await })
+        }
+    }
+
+    fn is_secure(&self, req: &Request) -> bool {
+        req.is_secure()
+            || (self.trust_forwarded_proto
+                && req
+                    .header("X-Forwarded-Proto")
+                    .is_some_and(|proto| proto.eq_ignore_ascii_case("https")))
+    }
+}
+
+async fn enforce(config: &HttpsConfig, req: Request, res: Response, next: Next) -> Response {
+    if config.is_secure(&req) {
+        return next().await;
+    }
+
+    let redirectable = matches!(req.method(), "GET" | "HEAD");
+    match (config.mode, req.header("Host")) {
+        (HttpsMode::Redirect, Some(host)) if redirectable => {
+            let location = match &req.query_string {
+                Some(query) => format!("https://{}{}?{}", host, req.path(), query),
+                None => format!("https://{}{}", host, req.path()),
+            };
+            res.redirect_with_status(301, &location)
+        }
+        _ => res.status(403).send("Forbidden: HTTPS required"),
+    }
+}
@@ -1,12 +1,44 @@
 /// Logger middleware - logs incoming requests
+///
+/// `logger` and `json_logger` record the response status, body size in bytes
+/// and handling duration. The size counts in-memory and file bodies; a
+/// streamed body's size isn't known yet and is logged as `-` (`null` in
+/// JSON). They report the response as returned by the rest of the chain, so
+/// register them *before* middleware that rewrites the body (e.g.
+/// compression) to log the bytes actually sent.
 use crate::{
-    http::{request::Request, response::Response},
+    http::{headers::Headers, json_value::escape, request::Request, response::Response},
     types::Next,
 };
+use std::time::{Duration, Instant};
 
 pub async fn logger(req: Request, _res: Response, next: Next) -> Response {
-    println!("📝 {} {}", req.method(), req.path());
-    next().await
+    let start = Instant::now();
+    let res = next().await;
+    println!(
+        "📝 {} {} {} {} {:.2}ms",
+        req.method(),
+        req.path(),
+        res.status_code,
+        body_size(&res).map_or("-".to_string(), |size| format!("{}B", size)),
+        millis(start.elapsed())
+    );
+    res
+}
+
+/// Access log as one JSON object per line
+pub async fn json_logger(req: Request, _res: Response, next: Next) -> Response {
+    let start = Instant::now();
+    let res = next().await;
+    println!(
+        r#"{{"method":"{}","path":"{}","status":{},"bytes":{},"duration_ms":{:.3}}}"#,
+        escape(req.method()),
+        escape(req.path()),
+        res.status_code,
+        body_size(&res).map_or("null".to_string(), |size| size.to_string()),
+        millis(start.elapsed())
+    );
+    res
 }
 
 pub async fn detailed_logger(req: Request, _res: Response, next: Next) -> Response {
@@ -36,3 +68,247 @@ pub fn with_prefix(
         })
     }
 }
+
+/// Log request and response bodies with the default `BodyLoggerConfig`
+pub async fn body_logger(req: Request, _res: Response, next: Next) -> Response {
+    log_bodies(&BodyLoggerConfig::default(), req, next).await
+}
+
+/// Opt-in logging of headers and bodies, for debugging
+///
+/// Bodies are cut at `max_len` characters with an ellipsis. Binary bodies,
+/// files and streams are logged by size only, and the values of `redact`
+/// names are masked wherever they appear: header names, JSON keys and
+/// form/query fields, compared case-insensitively.
+pub struct BodyLoggerConfig {
+    pub max_len: usize,
+    pub redact: Vec<String>,
+}
+
+impl Default for BodyLoggerConfig {
+    fn default() -> Self {
+        Self {
+            max_len: 1024,
+            redact: [
+                "authorization",
+                "cookie",
+                "set-cookie",
+                "password",
+                "token",
+                "secret",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        }
+    }
+}
+
+impl BodyLoggerConfig {
+    /// Create a new body logger configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of characters logged per body
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Set the header and field names whose values are masked
+    pub fn redact(mut self, names: Vec<String>) -> Self {
+        self.redact = names;
+        self
+    }
+
+    /// Build the middleware function
+    pub fn build(
+        self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + 'static {
+        let config = std::sync::Arc::new(self);
+        move |req: Request, _res: Response, next: Next| {
+            let config = config.clone();
+            Box::pin(async move { log_bodies(&config, req, next).await })
+        }
+    }
+
+    /// A response body as logged, see `render`; files and streams by size only
+    pub fn render_response(&self, res: &Response) -> String {
+        if let Some(file) = &res.file {
+            return format!("(file, {} bytes)", file.len);
+        }
+        if res.stream.is_some() {
+            return "(streamed)".to_string();
+        }
+        self.render(res.body.as_deref(), res.header("Content-Type"))
+    }
+
+    fn is_redacted(&self, name: &str) -> bool {
+        self.redact.iter().any(|r| r.eq_ignore_ascii_case(name))
+    }
+
+    /// Headers as logged, `Name: value` joined by commas, masking redacted names
+    pub fn render_headers(&self, headers: &Headers) -> String {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                if self.is_redacted(name) {
+                    format!("{}: ***", name)
+                } else {
+                    format!("{}: {}", name, value)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// A body as logged: redacted, then truncated to `max_len` characters
+    ///
+    /// Bodies that aren't UTF-8 or have a non-text Content-Type are logged
+    /// as `(binary, N bytes)`, never their content.
+    pub fn render(&self, body: Option<&[u8]>, content_type: Option<&String>) -> String {
+        let Some(bytes) = body.filter(|body| !body.is_empty()) else {
+            return "(empty)".to_string();
+        };
+        let body = match std::str::from_utf8(bytes) {
+            Ok(body) if is_text(content_type, body) => body,
+            _ => return format!("(binary, {} bytes)", bytes.len()),
+        };
+
+        let redacted = redact(body, &self.redact);
+        match redacted.char_indices().nth(self.max_len) {
+            Some((cut, _)) => format!("{}…", &redacted[..cut]),
+            None => redacted,
+        }
+    }
+}
+
+async fn log_bodies(config: &BodyLoggerConfig, req: Request, next: Next) -> Response {
+    let target = match &req.query_string {
+        Some(query) => format!("{}?{}", req.path(), redact(query, &config.redact)),
+        None => req.path().to_string(),
+    };
+    println!(
+        "📝 [Body] {} {} - Headers: [{}]",
+        req.method(),
+        target,
+        config.render_headers(&req.headers)
+    );
+    println!(
+        "   → {}",
+        config.render(
+            req.body().map(|body| body.as_bytes()),
+            req.header("Content-Type")
+        )
+    );
+
+    let res = next().await;
+    println!("   ← {} {}", res.status_code, config.render_response(&res));
+    res
+}
+
+/// Whether a body is safe to print: a textual Content-Type, or no type and
+/// no control characters
+pub(crate) fn is_text(content_type: Option<&String>, body: &str) -> bool {
+    let Some(content_type) = content_type else {
+        return !body
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\t' | '\r' | '\n'));
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/x-www-form-urlencoded"
+        )
+}
+
+/// Mask the values of `names` in JSON (`"name": value`) and form (`name=value`) text
+fn redact(text: &str, names: &[String]) -> String {
+    let lower = text.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+    let skip_space = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        i
+    };
+
+    let mut spans = Vec::new();
+    for name in names.iter().map(|name| name.to_ascii_lowercase()) {
+        let key = format!("\"{}\"", name);
+        for (at, _) in lower.match_indices(&key) {
+            let colon = skip_space(at + key.len());
+            if bytes.get(colon) != Some(&b':') {
+                continue;
+            }
+            let start = skip_space(colon + 1);
+            let (start, end) = if bytes.get(start) == Some(&b'"') {
+                // String value: mask up to the closing unescaped quote
+                let mut i = start + 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                (start + 1, i.min(bytes.len()))
+            } else {
+                let end = (start..bytes.len())
+                    .find(|&i| {
+                        matches!(bytes[i], b',' | b'}' | b']') || bytes[i].is_ascii_whitespace()
+                    })
+                    .unwrap_or(bytes.len());
+                (start, end)
+            };
+            spans.push((start, end));
+        }
+
+        let key = format!("{}=", name);
+        for (at, _) in lower.match_indices(&key) {
+            if at == 0 || bytes[at - 1] == b'&' {
+                let start = at + key.len();
+                let end = lower[start..].find('&').map_or(bytes.len(), |i| start + i);
+                spans.push((start, end));
+            }
+        }
+    }
+
+    spans.sort_unstable();
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, end) in spans {
+        if start < copied {
+            continue;
+        }
+        out.push_str(&text[copied..start]);
+        out.push_str("***");
+        copied = end;
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+/// Bytes the body will be written with, `None` for a stream
+fn body_size(res: &Response) -> Option<u64> {
+    res.content_length()
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
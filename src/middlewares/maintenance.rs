@@ -0,0 +1,119 @@
+/// Maintenance mode: answer 503 while a shared flag is set
+use crate::{
+    http::{request::Request, response::Response},
+    types::Next,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Switch shared between the app and the maintenance middleware
+///
+/// Clones share one atomic flag, so `Expresso::set_maintenance` or `set` on
+/// any clone takes effect for the next request on every connection.
+#[derive(Clone, Debug, Default)]
+pub struct MaintenanceFlag(Arc<AtomicBool>);
+
+impl MaintenanceFlag {
+    /// Create a flag that starts off
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn maintenance mode on or off
+    pub fn set(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Whether maintenance mode is on
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Answer 503 while `flag` is set, except for `/health`
+pub fn maintenance(
+    flag: MaintenanceFlag,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + 'static {
+    MaintenanceConfig::new(flag).build()
+}
+
+/// Custom maintenance mode configuration
+///
+/// While the flag is set, every request except those for the `allow` paths
+/// (health checks, status pages) gets `503 Service Unavailable` with
+/// `Retry-After`, without reaching later middleware or routes. Requests
+/// already past the middleware when the flag flips run to completion.
+pub struct MaintenanceConfig {
+    /// Switch watched on every request
+    pub flag: MaintenanceFlag,
+    /// Paths served normally during maintenance, matched exactly
+    pub allow: Vec<String>,
+    /// Seconds sent in `Retry-After`
+    pub retry_after: u64,
+    /// Plain-text body of the 503
+    pub message: String,
+}
+
+impl MaintenanceConfig {
+    /// Create a configuration watching `flag`, allowing `/health`
+    pub fn new(flag: MaintenanceFlag) -> Self {
+        Self {
+            flag,
+            allow: vec!["/health".to_string()],
+            retry_after: 120,
+            message: "Service temporarily down for maintenance".to_string(),
+        }
+    }
+
+    /// Set the paths that bypass maintenance mode
+    pub fn allow(mut self, paths: Vec<String>) -> Self {
+        self.allow = paths;
+        self
+    }
+
+    /// Set the `Retry-After` delay in seconds
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = seconds;
+        self
+    }
+
+    /// Set the 503 body text
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = message.to_string();
+        self
+    }
+
+    /// Build the middleware function
+    pub fn build(
+        self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + 'static {
+        let config = Arc::new(self);
+        move |req: Request, _res: Response, next: Next| {
+            let config = config.clone();
+            Box::pin(async move {
+                let allowed = config.allow.iter().any(|path| path == req.path());
+                if !config.flag.is_enabled() || allowed {
+                    return next().await;
+                }
+                Response::error(503, &config.message)
+                    .set_header("Retry-After", &config.retry_after.to_string())
+            })
+        }
+    }
+}
@@ -1,2 +1,10 @@
+pub mod concurrency;
+pub mod compression;
 pub mod cors;
+pub mod dump;
+pub mod embedded;
+pub mod https;
 pub mod logger;
+pub mod maintenance;
+pub mod spa;
+pub mod static_files;
@@ -0,0 +1,120 @@
+/// Single-page-app fallback middleware
+use crate::{
+    http::{request::Request, response::Response},
+    types::Next,
+};
+use std::{path::PathBuf, sync::Arc};
+
+/// Predicate deciding which requests never receive the SPA index
+pub type SkipFn = Arc<dyn Fn(&Request) -> bool + Send + Sync>;
+
+/// Serve `index_path` for unmatched HTML navigations, outside `/api/`
+pub fn spa_fallback(
+    index_path: &str,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + 'static {
+    SpaConfig::new(index_path).build()
+}
+
+/// Custom SPA fallback configuration
+///
+/// The index is served with 200 when no route matched, the request is a
+/// GET/HEAD whose `Accept` includes `text/html`, and the request is not
+/// skipped. By default a request is skipped when its path starts with one of
+/// `api_prefixes` or its last segment has a file extension (a missing asset
+/// should stay a 404). `skip_when` replaces that heuristic.
+pub struct SpaConfig {
+    pub index: PathBuf,
+    pub api_prefixes: Vec<String>,
+    pub skip_assets: bool,
+    pub skip: Option<SkipFn>,
+}
+
+impl SpaConfig {
+    /// Create a configuration serving the given index file
+    pub fn new(index_path: &str) -> Self {
+        Self {
+            index: PathBuf::from(index_path),
+            api_prefixes: vec!["/api/".to_string()],
+            skip_assets: true,
+            skip: None,
+        }
+    }
+
+    /// Set the path prefixes treated as API requests
+    pub fn api_prefixes(mut self, prefixes: Vec<String>) -> Self {
+        self.api_prefixes = prefixes;
+        self
+    }
+
+    /// Set whether paths with a file extension are treated as assets
+    pub fn skip_assets(mut self, skip: bool) -> Self {
+        self.skip_assets = skip;
+        self
+    }
+
+    /// Replace the API/asset heuristic with a custom predicate
+    pub fn skip_when<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        self.skip = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Build the middleware function
+    pub fn build(
+        self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + 'static {
+        let config = Arc::new(self);
+        move |req: Request, _res: Response, next: Next| {
+            let config = config.clone();
+            Box::pin(async move {
+                let res = next().await;
+                if res.unmatched && config.wants_index(&req) {
+                    Response::new().status(200).send_file(&config.index).await
+                } else {
+                    res
+                }
+            })
+        }
+    }
+
+    fn wants_index(&self, req: &Request) -> bool {
+        let navigation = matches!(req.method(), "GET" | "HEAD")
+            && req
+                .header("Accept")
+                .is_some_and(|accept| accept.contains("text/html"));
+        navigation && !self.is_skipped(req)
+    }
+
+    fn is_skipped(&self, req: &Request) -> bool {
+        if let Some(skip) = &self.skip {
+            return skip(req);
+        }
+        let path = req.path();
+        let is_api = self
+            .api_prefixes
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()));
+        let is_asset = self.skip_assets
+            && path
+                .rsplit('/')
+                .next()
+                .is_some_and(|segment| segment.contains('.'));
+        is_api || is_asset
+    }
+}
@@ -0,0 +1,171 @@
+/// Serving files from a directory on disk
+use crate::{
+    http::{
+        cache_control::CacheControl, etag, request::Request, response::Response,
+        url::percent_decode,
+    },
+    middlewares::embedded::is_hashed,
+    types::Next,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Serve the files under `dir` at the root of the site
+///
+/// ```ignore
+/// app.use_middleware(static_files::serve("public")).await;
+/// ```
+pub fn serve(
+    dir: &str,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + 'static {
+    StaticConfig::new(dir).build()
+}
+
+/// Static file configuration
+///
+/// GET and HEAD requests below `mount` are mapped to files under `root` and
+/// streamed with `Response::send_file`, which sets `Content-Length` and
+/// guesses the `Content-Type` from the extension. A directory path serves
+/// its `index` file. Paths with a `..` segment, even percent-encoded, are
+/// answered with 403 so nothing outside `root` can be reached. Missing files
+/// fall through to `next`, so routes and the app's 404 still apply.
+///
+/// Each file gets a weak `ETag` from its size and modification time, and
+/// `If-None-Match` is answered with 304. Unless `cache_control` is set,
+/// file names carrying a content hash (`app.3f9a2c1b.js`) are cached for a
+/// year as immutable and all others are sent with `no-cache`.
+pub struct StaticConfig {
+    pub root: PathBuf,
+    /// URL prefix the files are served under
+    pub mount: String,
+    /// File served for directory paths, if any
+    pub index: Option<String>,
+    /// Fixed `Cache-Control` for every file, replacing the default by name
+    pub cache_control: Option<CacheControl>,
+}
+
+impl StaticConfig {
+    /// Create a configuration serving `dir` at `/`
+    pub fn new(dir: &str) -> Self {
+        Self {
+            root: PathBuf::from(dir),
+            mount: "/".to_string(),
+            index: Some("index.html".to_string()),
+            cache_control: None,
+        }
+    }
+
+    /// Serve the files below a URL prefix, such as `/static`
+    pub fn mount(mut self, prefix: &str) -> Self {
+        self.mount = prefix.to_string();
+        self
+    }
+
+    /// Set the file served for directory paths, or `None` to serve none
+    pub fn index(mut self, index: Option<&str>) -> Self {
+        self.index = index.map(str::to_string);
+        self
+    }
+
+    /// Send the same `Cache-Control` with every file
+    pub fn cache_control(mut self, directives: CacheControl) -> Self {
+        self.cache_control = Some(directives);
+        self
+    }
+
+    /// Build the middleware function
+    pub fn build(
+        self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + 'static {
+        let config = Arc::new(self);
+        move |req: Request, _res: Response, next: Next| {
+            let config = config.clone();
+            Box::pin(async move {
+                if !matches!(req.method(), "GET" | "HEAD") {
+                    return next().await;
+                }
+                let Some(rest) = config.relative(req.path()) else {
+                    return next().await;
+                };
+                let Some(path) = config.resolve(rest) else {
+                    return Response::from_status(403);
+                };
+                match config.file(path).await {
+                    Some((path, metadata)) => config.respond(&req, &path, &metadata).await,
+                    None => next().await,
+                }
+            })
+        }
+    }
+
+    /// The request path below the mount, or `None` outside it
+    fn relative<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let rest = path.strip_prefix(self.mount.trim_end_matches('/'))?;
+        (rest.is_empty() || rest.starts_with('/')).then_some(rest)
+    }
+
+    /// Map the decoded segments onto `root`, refusing any that climb out
+    fn resolve(&self, rest: &str) -> Option<PathBuf> {
+        let mut path = self.root.clone();
+        for segment in rest.split('/').filter(|segment| !segment.is_empty()) {
+            let segment = percent_decode(segment);
+            if segment == ".." || segment.contains(['/', '\\', '\0']) {
+                return None;
+            }
+            if segment != "." {
+                path.push(segment);
+            }
+        }
+        Some(path)
+    }
+
+    /// The regular file to serve for `path`, trying the index for directories
+    async fn file(&self, path: PathBuf) -> Option<(PathBuf, std::fs::Metadata)> {
+        let metadata = tokio::fs::metadata(&path).await.ok()?;
+        if metadata.is_file() {
+            return Some((path, metadata));
+        }
+        let index = path.join(self.index.as_ref().filter(|_| metadata.is_dir())?);
+        let metadata = tokio::fs::metadata(&index).await.ok()?;
+        metadata.is_file().then_some((index, metadata))
+    }
+
+    async fn respond(&self, req: &Request, path: &Path, metadata: &std::fs::Metadata) -> Response {
+        let cache_control = self.cache_control.clone().unwrap_or_else(|| {
+            let name = path.to_string_lossy();
+            if is_hashed(&name) {
+                CacheControl::immutable_asset()
+            } else {
+                CacheControl::new().no_cache()
+            }
+        });
+        let mut res = Response::new().cache_control(cache_control);
+        if let Ok(modified) = metadata.modified() {
+            let tag = etag::from_metadata(metadata.len(), modified);
+            if req
+                .header("If-None-Match")
+                .is_some_and(|header| etag::none_match(header, &tag))
+            {
+                return res.set_header("ETag", &tag).status(304);
+            }
+            res = res.set_header("ETag", &tag);
+        }
+        res.send_file(path).await
+    }
+}
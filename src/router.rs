@@ -1,8 +1,8 @@
-use crate::types::Handler;
+use crate::{http::url::percent_decode, types::Handler};
 use std::collections::HashMap;
 use tokio::sync::RwLock;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Method {
     GET,
     POST,
@@ -11,9 +11,15 @@ pub enum Method {
     PATCH,
     HEAD,
     OPTIONS,
+    TRACE,
+    CONNECT,
+    /// Matches every method not registered specifically for the path
+    /// Only used when registering routes; never parsed from a request
+    ANY,
 }
 
 impl Method {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_uppercase().as_str() {
             "GET" => Some(Method::GET),
@@ -23,6 +29,8 @@ impl Method {
             "PATCH" => Some(Method::PATCH),
             "HEAD" => Some(Method::HEAD),
             "OPTIONS" => Some(Method::OPTIONS),
+            "TRACE" => Some(Method::TRACE),
+            "CONNECT" => Some(Method::CONNECT),
             _ => None,
         }
     }
@@ -36,43 +44,279 @@ impl Method {
             Method::PATCH => "PATCH",
             Method::HEAD => "HEAD",
             Method::OPTIONS => "OPTIONS",
+            Method::TRACE => "TRACE",
+            Method::CONNECT => "CONNECT",
+            Method::ANY => "ANY",
         }
     }
 }
 
+/// Path parameters captured by a patterned route, percent-decoded
+pub type Params = HashMap<String, String>;
+
 pub struct Router {
-    routes: RwLock<HashMap<String, Handler>>,
+    routes: RwLock<RouteTable>,
+}
+
+/// Handlers keyed by method and path, plus the methods of each path
+#[derive(Default)]
+struct RouteTable {
+    handlers: HashMap<String, Handler>,
+    /// Routes with `:name` segments, tried when no static route matches
+    patterns: Vec<PatternRoute>,
+    /// Secondary index kept in `Method` order, so `Allow` needs no scan
+    methods: HashMap<String, Vec<Method>>,
+}
+
+struct PatternRoute {
+    method: Method,
+    pattern: Pattern,
+    handler: Handler,
+}
+
+impl RouteTable {
+    fn insert(&mut self, method: Method, path: &str, handler: Handler) {
+        match Pattern::parse(path) {
+            Some(pattern) => {
+                // Registering the same pattern again replaces it, like a static route
+                self.patterns
+                    .retain(|route| route.method != method || route.pattern.text != path);
+                self.patterns.push(PatternRoute {
+                    method,
+                    pattern,
+                    handler,
+                });
+            }
+            None => {
+                self.handlers
+                    .insert(route_key(method.as_str(), path), handler);
+            }
+        }
+        let methods = self.methods.entry(path.to_string()).or_default();
+        if let Err(at) = methods.binary_search(&method) {
+            methods.insert(at, method);
+        }
+    }
+
+    /// The static route for `method` and `path`, else the best matching pattern
+    fn lookup(&self, method: &str, path: &str) -> Option<(Handler, Params)> {
+        if let Some(handler) = self.handlers.get(&route_key(method, path)) {
+            return Some((handler.clone(), Params::new()));
+        }
+        let mut best: Option<(&PatternRoute, Params, Rank)> = None;
+        for route in self.patterns.iter().filter(|r| r.method.as_str() == method) {
+            let Some((params, rank)) = route.pattern.matches(path) else {
+                continue;
+            };
+            // Ties go to the route registered first
+            if best.as_ref().is_none_or(|(_, _, best)| rank > *best) {
+                best = Some((route, params, rank));
+            }
+        }
+        best.map(|(route, params, _)| (route.handler.clone(), params))
+    }
 }
 
 impl Router {
     /// Create a new empty router
     pub fn new() -> Self {
         Self {
-            routes: RwLock::new(HashMap::new()),
+            routes: RwLock::new(RouteTable::default()),
         }
     }
 
     /// Register a route with a handler
+    ///
+    /// A `:name` segment captures one non-empty path segment into the
+    /// request params; a final `:name?` segment may also be absent, in which
+    /// case the param is left out (`/posts/:year/:month?` matches both
+    /// `/posts/2024` and `/posts/2024/03`). A `?` on an earlier segment is
+    /// ignored, making it a required param. A final `*name` segment captures
+    /// the rest of the path as is, slashes included and not percent-decoded,
+    /// and may be empty (`/static/*path` matches `/static/css/app.css` with
+    /// `css/app.css`, and `/static/` with an empty tail).
     pub async fn add_route(&self, method: Method, path: &str, handler: Handler) {
-        let key = format!("{}:{}", method.as_str(), path);
-        let mut routes = self.routes.write().await;
-        routes.insert(key, handler);
+        self.routes.write().await.insert(method, path, handler);
+    }
+
+    /// Register a route without locking, for setup before the router is shared
+    pub fn insert_route(&mut self, method: Method, path: &str, handler: Handler) {
+        self.routes.get_mut().insert(method, path, handler);
     }
 
     /// Find a handler for the given method and path
+    /// See `find_route` for the params and precedence
     pub async fn find_handler(&self, method: &str, path: &str) -> Option<Handler> {
-        let key = format!("{}:{}", method, path);
+        self.find_route(method, path)
+            .await
+            .map(|(handler, _)| handler)
+    }
+
+    /// Find a handler with the params captured from `path`
+    ///
+    /// Routes for the exact method win over `ANY` routes, and HEAD requests
+    /// without a HEAD route use the GET route before those. Among them, a
+    /// static route always wins; otherwise the pattern with a static segment
+    /// furthest left where they differ wins (`/posts/latest/:page` over
+    /// `/posts/:year/:month?`), then one matching every segment over one
+    /// omitting its optional segment, then the route registered first.
+    /// Wildcard patterns rank below all others, the longest prefix first.
+    pub async fn find_route(&self, method: &str, path: &str) -> Option<(Handler, Params)> {
+        let routes = self.routes.read().await;
+        routes
+            .lookup(method, path)
+            .or_else(|| {
+                (method == Method::HEAD.as_str())
+                    .then(|| routes.lookup(Method::GET.as_str(), path))
+                    .flatten()
+            })
+            .or_else(|| routes.lookup(Method::ANY.as_str(), path))
+    }
+
+    /// Methods with a route matching `path`, in `Method` order
+    /// HEAD is listed wherever GET is, since it runs the GET route
+    /// `*` lists every method registered on any path, for `OPTIONS *`
+    pub async fn methods_for_path(&self, path: &str) -> Vec<Method> {
         let routes = self.routes.read().await;
-        routes.get(&key).cloned()
+        let mut methods: Vec<Method> = if path == "*" {
+            routes.methods.values().flatten().copied().collect()
+        } else {
+            let patterned = routes
+                .patterns
+                .iter()
+                .filter(|route| route.pattern.matches(path).is_some())
+                .map(|route| route.method);
+            match routes.methods.get(path) {
+                // The index holds the pattern text too, so skip pattern methods there
+                Some(exact) if Pattern::parse(path).is_none() => {
+                    exact.iter().copied().chain(patterned).collect()
+                }
+                _ => patterned.collect(),
+            }
+        };
+        if methods.contains(&Method::GET) {
+            methods.push(Method::HEAD);
+        }
+        methods.sort();
+        methods.dedup();
+        methods
     }
 
     /// Get all registered routes (useful for debugging)
     pub async fn get_all_routes(&self) -> Vec<String> {
         let routes = self.routes.read().await;
-        routes.keys().cloned().collect()
+        let patterned = routes
+            .patterns
+            .iter()
+            .map(|route| route_key(route.method.as_str(), &route.pattern.text));
+        routes.handlers.keys().cloned().chain(patterned).collect()
+    }
+}
+
+/// One `/`-separated piece of a route pattern
+enum Segment {
+    Static(String),
+    Param(String),
+    /// Trailing param that may be absent
+    Optional(String),
+    /// Trailing `*name` that captures the rest of the path
+    Wildcard(String),
+}
+
+/// Specificity of a match, compared lexicographically: whether it used no
+/// wildcard, one entry per path segment before any wildcard (static 1,
+/// param 0), then whether no optional segment was omitted
+type Rank = (bool, Vec<u8>, bool);
+
+/// A route path with at least one `:name` segment or a trailing `*name`
+struct Pattern {
+    text: String,
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    /// Parse `path`, or `None` when it has no params and is a static route
+    fn parse(path: &str) -> Option<Self> {
+        let count = path.split('/').count();
+        let segments: Vec<Segment> = path
+            .split('/')
+            .enumerate()
+            .map(|(at, segment)| {
+                let last = at + 1 == count;
+                match (segment.strip_prefix(':'), segment.strip_prefix('*')) {
+                    (Some(name), _) => match name.strip_suffix('?') {
+                        Some(name) if last => Segment::Optional(name.to_string()),
+                        Some(name) => Segment::Param(name.to_string()),
+                        None => Segment::Param(name.to_string()),
+                    },
+                    (None, Some(name)) if last => Segment::Wildcard(name.to_string()),
+                    _ => Segment::Static(segment.to_string()),
+                }
+            })
+            .collect();
+        if segments
+            .iter()
+            .all(|segment| matches!(segment, Segment::Static(_)))
+        {
+            return None;
+        }
+        Some(Self {
+            text: path.to_string(),
+            segments,
+        })
+    }
+
+    /// Params captured from `path` and the match's rank, if it matches
+    fn matches(&self, path: &str) -> Option<(Params, Rank)> {
+        let mut parts: Vec<&str> = path.split('/').collect();
+        let mut params = Params::new();
+        let wildcard = match self.segments.last() {
+            Some(Segment::Wildcard(name)) => Some(name),
+            _ => None,
+        };
+        if let Some(name) = wildcard {
+            // The tail keeps its slashes and escapes exactly as requested
+            let prefix = self.segments.len() - 1;
+            if parts.len() <= prefix {
+                return None;
+            }
+            params.insert(name.clone(), parts[prefix..].join("/"));
+            parts.truncate(prefix);
+        }
+        let segments = &self.segments[..self.segments.len() - usize::from(wildcard.is_some())];
+
+        let omitted = match parts.len() {
+            n if n == segments.len() => false,
+            n if n + 1 == segments.len()
+                && matches!(segments.last(), Some(Segment::Optional(_))) =>
+            {
+                true
+            }
+            _ => return None,
+        };
+
+        let mut rank = Vec::with_capacity(parts.len());
+        for (segment, part) in segments.iter().zip(&parts) {
+            match segment {
+                Segment::Static(text) if text == part => rank.push(1),
+                // Wildcards were split off above, as they only end a pattern
+                Segment::Static(_) | Segment::Wildcard(_) => return None,
+                Segment::Param(_) | Segment::Optional(_) if part.is_empty() => return None,
+                Segment::Param(name) | Segment::Optional(name) => {
+                    params.insert(name.clone(), percent_decode(part));
+                    rank.push(0);
+                }
+            }
+        }
+        Some((params, (wildcard.is_none(), rank, !omitted)))
     }
 }
 
+/// Build the lookup key used by the route table
+fn route_key(method: &str, path: &str) -> String {
+    format!("{}:{}", method, path)
+}
+
 impl Default for Router {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,301 @@
+use std::time::Duration;
+
+/// Server configuration
+#[derive(Clone)]
+pub struct ServerConfig {
+    /// Accept IPv4 connections on an IPv6 wildcard address (`[::]`)
+    ///
+    /// Clears `IPV6_V6ONLY` so IPv4 clients arrive as IPv4-mapped IPv6
+    /// addresses. Only meaningful when binding an IPv6 address. Platform
+    /// defaults differ (Linux follows `net.ipv6.bindv6only`, Windows and the
+    /// BSDs default to IPv6-only) and some systems, such as OpenBSD, do not
+    /// support dual-stack sockets at all, in which case binding fails.
+    pub dual_stack: bool,
+
+    /// Length of the kernel queue of connections waiting to be accepted
+    /// (default none, the standard library's choice)
+    ///
+    /// Raise it when bursts of new connections get refused or reset; the
+    /// kernel caps it at its own limit (`net.core.somaxconn` on Linux).
+    /// Setting it binds through `socket2`, as dual-stack binding does.
+    pub backlog: Option<u32>,
+
+    /// Most connections accepted in one go before checking for shutdown and
+    /// yielding (default 1)
+    ///
+    /// Larger values drain a burst of pending connections in a tight loop,
+    /// which helps under heavy connection churn at the cost of fairness to
+    /// other tasks on the accepting thread.
+    pub accept_batch: usize,
+
+    /// Cancel the handler when the client closes the connection (default `false`)
+    ///
+    /// The handler future is dropped at its current `.await`, so handlers must
+    /// be cancellation-safe: release resources through `Drop` (e.g. roll back
+    /// a transaction guard on drop) rather than code after an await. Clients
+    /// that half-close their write side after sending a request also look
+    /// disconnected, so only enable this when no client does that.
+    pub cancel_on_disconnect: bool,
+
+    /// Dispatch `TRACE` requests instead of rejecting them with 405 (default `false`)
+    /// Left off by default to prevent cross-site tracing (XST)
+    pub allow_trace: bool,
+
+    /// Buffer size in bytes used to stream `Response::send_file` bodies (default 64 KiB)
+    pub file_chunk_size: usize,
+
+    /// Upper bound on the time to answer a request (default none)
+    ///
+    /// Handlers read the resulting deadline through `Request::deadline` and
+    /// `Request::remaining`. When it passes, the handler is dropped and the
+    /// client receives 503. A client's `X-Request-Timeout` header can shorten
+    /// the deadline but never extend it.
+    pub request_timeout: Option<Duration>,
+
+    /// How long shutdown waits for in-flight requests and background tasks
+    /// (default none, waiting for all of them)
+    /// Whatever is still running afterwards is left behind when `listen` returns
+    pub shutdown_grace: Option<Duration>,
+
+    /// How long a connection may wait for the first byte of a request (default 60 s)
+    /// Covers fresh and idle keep-alive connections; they are closed silently
+    pub idle_timeout: Option<Duration>,
+
+    /// How long a partly received request may go without new bytes (default 30 s)
+    /// A stalled head or body (slowloris) gets 408 and the connection is closed
+    pub read_timeout: Option<Duration>,
+
+    /// Initial size in bytes of the per-connection read buffer (default 4 KiB)
+    /// The buffer doubles as a larger request arrives, up to `max_request_size`
+    pub read_buffer_size: usize,
+
+    /// Largest request, head plus body, the server reads (default 1 MiB)
+    /// Larger requests get 431 if the head does not fit, otherwise 413
+    pub max_request_size: usize,
+
+    /// Largest request body accepted, in bytes (default 1 MiB)
+    /// A declared `Content-Length` over it gets 413 before the body is read,
+    /// and a chunked body once it is decoded. While arriving, every body is
+    /// also bounded by `max_request_size` along with the head.
+    pub max_body_size: usize,
+
+    /// Longest single header value accepted, in bytes (default 16 KiB)
+    /// A request with a longer value, such as a giant cookie, gets 431
+    pub max_header_value_size: usize,
+
+    /// Largest request body accepted after `Content-Encoding` decoding (default 1 MiB)
+    ///
+    /// Bodies sent with `gzip` or `deflate` are decompressed before parsing;
+    /// one that inflates past this limit (a zip bomb) gets 413. Other codings
+    /// get 415 and corrupt data 400.
+    pub max_decompressed_size: usize,
+
+    /// Limits applied by `Request::json` (default 64 levels, 1 MiB)
+    pub json_limits: JsonLimits,
+
+    /// Collect `Request::time` spans and send them in a `Server-Timing`
+    /// header (default `false`)
+    /// Timing data reveals how long backends take, so enable it only where
+    /// clients may see that, such as in development
+    pub server_timing: bool,
+
+    /// Serve further requests on a connection after the first (default `true`)
+    ///
+    /// HTTP/1.1 connections stay open until the client sends
+    /// `Connection: close` or closes its end; HTTP/1.0 clients must ask with
+    /// `Connection: keep-alive`. A handler can end the connection by setting
+    /// `Connection: close` on its response. The last response on a
+    /// connection says `Connection: close`, and HTTP/1.0 responses that keep it
+    /// open say `Connection: keep-alive`. Requests on one connection share a
+    /// `ConnectionContext`. Idle connections are closed after `idle_timeout`
+    /// and on shutdown.
+    pub keep_alive: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            dual_stack: false,
+            backlog: None,
+            accept_batch: 1,
+            cancel_on_disconnect: false,
+            allow_trace: false,
+            file_chunk_size: 64 * 1024,
+            request_timeout: None,
+            shutdown_grace: None,
+            idle_timeout: Some(Duration::from_secs(60)),
+            read_timeout: Some(Duration::from_secs(30)),
+            read_buffer_size: 4 * 1024,
+            max_request_size: 1024 * 1024,
+            max_body_size: 1024 * 1024,
+            max_header_value_size: 16 * 1024,
+            max_decompressed_size: 1024 * 1024,
+            json_limits: JsonLimits::default(),
+            server_timing: false,
+            keep_alive: true,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Create a new server configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable dual-stack binding for IPv6 addresses
+    pub fn dual_stack(mut self, enabled: bool) -> Self {
+        self.dual_stack = enabled;
+        self
+    }
+
+    /// Set the listen backlog
+    pub fn backlog(mut self, backlog: u32) -> Self {
+        self.backlog = Some(backlog);
+        self
+    }
+
+    /// Set how many connections to accept per batch, clamped to at least one
+    pub fn accept_batch(mut self, size: usize) -> Self {
+        self.accept_batch = size.max(1);
+        self
+    }
+
+    /// Enable or disable cancelling handlers when the client disconnects
+    pub fn cancel_on_disconnect(mut self, enabled: bool) -> Self {
+        self.cancel_on_disconnect = enabled;
+        self
+    }
+
+    /// Allow or reject `TRACE` requests
+    pub fn allow_trace(mut self, allowed: bool) -> Self {
+        self.allow_trace = allowed;
+        self
+    }
+
+    /// Set the buffer size used to stream files, clamped to at least one byte
+    pub fn file_chunk_size(mut self, size: usize) -> Self {
+        self.file_chunk_size = size.max(1);
+        self
+    }
+
+    /// Set the request-wide deadline applied to every request
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how long shutdown waits for running work before returning
+    pub fn shutdown_grace(mut self, grace: Duration) -> Self {
+        self.shutdown_grace = Some(grace);
+        self
+    }
+
+    /// Set how long to wait for a request to start, or `None` to wait forever
+    pub fn idle_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.idle_timeout = timeout.into();
+        self
+    }
+
+    /// Set how long a request in progress may stall between reads, or `None`
+    /// to wait forever
+    pub fn read_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.read_timeout = timeout.into();
+        self
+    }
+
+    /// Set the initial read buffer size, clamped to at least one byte
+    pub fn read_buffer_size(mut self, size: usize) -> Self {
+        self.read_buffer_size = size.max(1);
+        self
+    }
+
+    /// Set the largest request the server reads, clamped to at least one byte
+    pub fn max_request_size(mut self, size: usize) -> Self {
+        self.max_request_size = size.max(1);
+        self
+    }
+
+    /// Set the largest request body accepted
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+
+    /// Set the longest accepted header value
+    pub fn max_header_value_size(mut self, size: usize) -> Self {
+        self.max_header_value_size = size;
+        self
+    }
+
+    /// Set the largest body accepted after decompression
+    pub fn max_decompressed_size(mut self, size: usize) -> Self {
+        self.max_decompressed_size = size;
+        self
+    }
+
+    /// Set the default limits for `Request::json`
+    pub fn json_limits(mut self, limits: JsonLimits) -> Self {
+        self.json_limits = limits;
+        self
+    }
+
+    /// Enable or disable the `Server-Timing` response header
+    pub fn server_timing(mut self, enabled: bool) -> Self {
+        self.server_timing = enabled;
+        self
+    }
+
+    /// Enable or disable serving several requests per connection
+    pub fn keep_alive(mut self, enabled: bool) -> Self {
+        self.keep_alive = enabled;
+        self
+    }
+}
+
+/// Bounds on untrusted JSON bodies, checked before deserializing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JsonLimits {
+    /// Deepest nesting of arrays and objects accepted
+    pub max_depth: usize,
+    /// Largest body accepted, in bytes
+    pub max_size: usize,
+    /// Media type the `Content-Type` must name; `application/json` also
+    /// accepts `+json` types, and `*/*` accepts any type or none
+    pub content_type: &'static str,
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 64,
+            max_size: 1024 * 1024,
+            content_type: "application/json",
+        }
+    }
+}
+
+impl JsonLimits {
+    /// Create the default limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the deepest nesting accepted
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Set the largest body accepted
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Set the media type the `Content-Type` must name, or `*/*` to skip the check
+    pub fn content_type(mut self, content_type: &'static str) -> Self {
+        self.content_type = content_type;
+        self
+    }
+}
@@ -0,0 +1,108 @@
+/// Per-connection state shared by every request on a keep-alive connection
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Data that lives as long as the TCP connection, see `Request::connection`
+///
+/// The server creates one context when it accepts a stream and hands a
+/// handle to every request read from it; the context is dropped once the
+/// connection closes and the last request holding a handle is gone. Values
+/// are keyed by type, like request extensions, and are shared: a value
+/// inserted while handling one request is visible to the next request on the
+/// same connection, and to middleware running for the current one.
+///
+/// ```ignore
+/// app.use_middleware(|req: Request, _res, next: Next| async move {
+///     let conn = req.connection();
+///     if conn.get::<Session>().is_none() {
+///         conn.insert(Session::from_headers(&req)?);
+///     }
+///     next().await
+/// })
+/// .await;
+/// ```
+#[derive(Clone, Default)]
+pub struct ConnectionContext {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    peer_addr: Option<SocketAddr>,
+    requests: AtomicU64,
+    values: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+}
+
+impl ConnectionContext {
+    pub(crate) fn new(peer_addr: SocketAddr) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                peer_addr: Some(peer_addr),
+                ..Inner::default()
+            }),
+        }
+    }
+
+    /// Count a request read from this connection
+    pub(crate) fn begin_request(&self) {
+        self.inner.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Address of the client, `None` for requests not read from a socket
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.inner.peer_addr
+    }
+
+    /// Number of requests read so far, including the current one
+    pub fn request_count(&self) -> u64 {
+        self.inner.requests.load(Ordering::Relaxed)
+    }
+
+    /// Store a value for the rest of the connection, returning the previous one
+    pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<T> {
+        self.values()
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok())
+            .map(|old| *old)
+    }
+
+    /// A copy of the stored value of type `T`
+    pub fn get<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+        self.values()
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// Remove and return the stored value of type `T`
+    pub fn remove<T: Send + Sync + 'static>(&self) -> Option<T> {
+        self.values()
+            .remove(&TypeId::of::<T>())
+            .and_then(|old| old.downcast().ok())
+            .map(|old| *old)
+    }
+
+    fn values(&self) -> std::sync::MutexGuard<'_, HashMap<TypeId, Box<dyn Any + Send + Sync>>> {
+        // A panicking handler can't leave the map half-updated, so poison is ignored
+        self.inner
+            .values
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl std::fmt::Debug for ConnectionContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionContext")
+            .field("peer_addr", &self.peer_addr())
+            .field("request_count", &self.request_count())
+            .finish_non_exhaustive()
+    }
+}
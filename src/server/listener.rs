@@ -1,43 +1,638 @@
-use crate::http::request::Request;
-use std::net::SocketAddr;
+use crate::{
+    errors::ErrorBodies,
+    http::{
+        hijack::Connection,
+        request::Request,
+        response::{reason_phrase, Response},
+        stream::Frame,
+    },
+    server::{
+        config::ServerConfig,
+        connection::ConnectionContext,
+        parser::{decode_content, expected_len, read_request_after, ReadOutcome},
+    },
+    types::ResponseHook,
+};
+use futures::FutureExt;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{future::Future, net::SocketAddr, sync::Arc};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpListener,
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    time::Instant,
 };
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+/// One address to accept connections on, see `Expresso::listen_multi`
+#[derive(Clone)]
+pub enum Listener {
+    /// Plain HTTP
+    Http(SocketAddr),
+    /// HTTPS, terminating TLS with the given configuration (requires the `tls` feature)
+    #[cfg(feature = "tls")]
+    Https(SocketAddr, crate::server::tls::TlsConfig),
+}
+
+impl Listener {
+    /// Address the listener binds to
+    pub fn addr(&self) -> SocketAddr {
+        match self {
+            Self::Http(addr) => *addr,
+            #[cfg(feature = "tls")]
+            Self::Https(addr, _) => *addr,
+        }
+    }
+}
 
 pub struct Server {
     addr: SocketAddr,
+    config: ServerConfig,
+    error_bodies: ErrorBodies,
+    shutdown: CancellationToken,
+    response_hook: Option<ResponseHook>,
+    tasks: TaskTracker,
+    #[cfg(feature = "tls")]
+    tls: Option<tokio_rustls::TlsAcceptor>,
 }
 
 impl Server {
     pub fn new(addr: SocketAddr) -> Self {
-        Self { addr }
+        Self {
+            addr,
+            config: ServerConfig::default(),
+            error_bodies: ErrorBodies::default(),
+            shutdown: CancellationToken::new(),
+            response_hook: None,
+            tasks: TaskTracker::new(),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Use the given server configuration
+    pub fn with_config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Use the given registry for responses the server generates itself
+    pub fn with_error_bodies(mut self, error_bodies: ErrorBodies) -> Self {
+        self.error_bodies = error_bodies;
+        self
+    }
+
+    /// Use the given token as the shutdown signal
+    /// Cancelling it stops the accept loop and is observable by every request
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Track connections on `tasks`, so shutdown also waits for other work
+    /// spawned on it, see `Expresso::spawn`
+    pub fn with_tasks(mut self, tasks: TaskTracker) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    /// Call `hook` after each response is written, see `Expresso::on_response`
+    pub fn with_response_hook(mut self, hook: ResponseHook) -> Self {
+        self.response_hook = Some(hook);
+        self
+    }
+
+    /// Terminate TLS on accepted connections, serving HTTPS
+    ///
+    /// The handshake must finish within `ServerConfig::read_timeout`, and
+    /// before shutdown; connections that fail it are closed silently. Requests report
+    /// `Request::is_secure`. Handlers can't hijack TLS connections: their
+    /// `Hijack` fails and the response is written normally.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, config: crate::server::tls::TlsConfig) -> Self {
+        self.tls = Some(tokio_rustls::TlsAcceptor::from(config));
+        self
     }
 
     pub async fn listen<H, F>(&self, handler: H) -> tokio::io::Result<()>
     where
         H: Fn(Request) -> F + Send + Sync + 'static + Clone,
-        F: std::future::Future<Output = crate::http::response::Response> + Send + 'static,
+        F: Future<Output = Response> + Send + 'static,
     {
-        let listener: TcpListener = TcpListener::bind(self.addr).await?;
-        loop {
-            let (mut stream, _addr) = listener.accept().await?;
+        let listener = self.bind().await?;
+        self.serve(listener, handler).await
+    }
+
+    /// Accept connections on an already bound listener until shutdown
+    pub async fn serve<H, F>(&self, listener: TcpListener, handler: H) -> tokio::io::Result<()>
+    where
+        H: Fn(Request) -> F + Send + Sync + 'static + Clone,
+        F: Future<Output = Response> + Send + 'static,
+    {
+        let config = Arc::new(self.config.clone());
+        let tasks = &self.tasks;
+        let spawn = |stream: TcpStream, addr| {
+            let context = ConnectionContext::new(addr);
             let handler = handler.clone();
+            let shutdown = self.shutdown.clone();
+            let config = config.clone();
+            let error_bodies = self.error_bodies.clone();
+            let hook = self.response_hook.clone();
+            #[cfg(feature = "tls")]
+            if let Some(acceptor) = self.tls.clone() {
+                tasks.spawn(async move {
+                    let handshake = async {
+                        match config.read_timeout {
+                            Some(limit) => tokio::time::timeout(limit, acceptor.accept(stream))
+                                .await
+                                .ok(),
+                            None => Some(acceptor.accept(stream).await),
+                        }
+                    };
+                    // A client that never finishes the handshake doesn't hold up shutdown
+                    let stream = tokio::select! {
+                        stream = handshake => stream?.ok()?,
+                        _ = shutdown.cancelled() => return None,
+                    };
+                    handle_connection(
+                        stream,
+                        context,
+                        handler,
+                        shutdown,
+                        config,
+                        error_bodies,
+                        hook,
+                    )
+                    .await
+                });
+                return;
+            }
+            tasks.spawn(handle_connection(
+                stream,
+                context,
+                handler,
+                shutdown,
+                config,
+                error_bodies,
+                hook,
+            ));
+        };
+        loop {
+            let (stream, addr) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                _ = self.shutdown.cancelled() => break,
+            };
+            spawn(stream, addr);
 
-            tokio::spawn(async move {
-                let mut buffer = vec![0; 4096];
-                let n = stream.read(&mut buffer).await.ok()?;
-                if n == 0 {
-                    return Some(());
+            // Drain connections that are already pending without waiting
+            for _ in 1..config.accept_batch {
+                let Some(accepted) = listener.accept().now_or_never() else {
+                    break;
+                };
+                let (stream, addr) = accepted?;
+                spawn(stream, addr);
+            }
+        }
+
+        // Let in-flight requests and tracked tasks observe the signal and finish
+        tasks.close();
+        match config.shutdown_grace {
+            Some(grace) => {
+                let _ = tokio::time::timeout(grace, tasks.wait()).await;
+            }
+            None => tasks.wait().await,
+        }
+        Ok(())
+    }
+
+    /// Bind the listening socket, clearing `IPV6_V6ONLY` for dual-stack and
+    /// applying `ServerConfig::backlog`
+    /// Port 0 picks an ephemeral port; read it back with `local_addr`
+    pub async fn bind(&self) -> tokio::io::Result<TcpListener> {
+        let dual_stack = self.config.dual_stack && self.addr.is_ipv6();
+        if !dual_stack && self.config.backlog.is_none() {
+            return TcpListener::bind(self.addr).await;
+        }
+
+        let socket = Socket::new(
+            Domain::for_address(self.addr),
+            Type::STREAM,
+            Some(Protocol::TCP),
+        )?;
+        if dual_stack {
+            socket.set_only_v6(false)?;
+        }
+        #[cfg(unix)]
+        socket.set_reuse_address(true)?;
+        socket.bind(&self.addr.into())?;
+        let backlog = self.config.backlog.unwrap_or(1024);
+        socket.listen(backlog.min(i32::MAX as u32) as i32)?;
+        socket.set_nonblocking(true)?;
+        TcpListener::from_std(socket.into())
+    }
+}
+
+/// A client connection requests are read from and responses written to
+trait Transport: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+    /// Whether the connection is encrypted, see `Request::is_secure`
+    const SECURE: bool;
+
+    /// The plain socket, for a handler that hijacks the connection
+    fn into_tcp(self) -> Result<TcpStream, Self>
+    where
+        Self: Sized;
+}
+
+impl Transport for TcpStream {
+    const SECURE: bool = false;
+
+    fn into_tcp(self) -> Result<TcpStream, Self> {
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Transport for tokio_rustls::server::TlsStream<TcpStream> {
+    const SECURE: bool = true;
+
+    /// The TLS session can't be handed over, so hijacking is refused
+    fn into_tcp(self) -> Result<TcpStream, Self> {
+        Err(self)
+    }
+}
+
+/// Serve the requests of one connection: a single one, or with
+/// `keep_alive` as many as the client sends until it asks to close
+async fn handle_connection<S, H, F>(
+    mut stream: S,
+    connection: ConnectionContext,
+    handler: H,
+    shutdown: CancellationToken,
+    config: Arc<ServerConfig>,
+    error_bodies: ErrorBodies,
+    hook: Option<ResponseHook>,
+) -> Option<()>
+where
+    S: Transport,
+    H: Fn(Request) -> F,
+    F: Future<Output = Response>,
+{
+    // Bytes read past the previous request: a pipelined request, or early
+    // data for a handler that takes over the connection
+    let mut buffered = Vec::new();
+    loop {
+        let mut carried = std::mem::take(&mut buffered);
+        // Only an idle connection gives way to shutdown: a new one until its
+        // first bytes arrive, a kept-alive one between requests
+        let outcome = if connection.request_count() == 0 {
+            if carried.is_empty() {
+                match first_bytes(&mut stream, &config, &shutdown).await {
+                    Some(bytes) => carried = bytes,
+                    None => return Some(()),
+                }
+            }
+            read_request_after(&mut stream, &config, carried)
+                .await
+                .ok()?
+        } else {
+            tokio::select! {
+                outcome = read_request_after(&mut stream, &config, carried) => outcome.ok()?,
+                _ = shutdown.cancelled() => return Some(()),
+            }
+        };
+        let started = Instant::now();
+        let read = match outcome {
+            ReadOutcome::Request(buffer) => Ok(buffer),
+            ReadOutcome::Closed => return Some(()),
+            ReadOutcome::TooLarge(code) | ReadOutcome::Rejected(code) => Err(code),
+            ReadOutcome::TimedOut => Err(408),
+        };
+        let mut buffer = match read {
+            Ok(buffer) => buffer,
+            Err(code) => {
+                let res = error_bodies
+                    .response(code, reason_phrase(code))
+                    .await
+                    .set_header("Connection", "close");
+                return respond(
+                    &mut stream,
+                    None,
+                    &res,
+                    false,
+                    started,
+                    &config,
+                    hook.as_ref(),
+                )
+                .await
+                .ok();
+            }
+        };
+        if let Some(len) = expected_len(&buffer).filter(|&len| len < buffer.len()) {
+            buffered = buffer.split_off(len);
+        }
+
+        let parsed = decode_content(buffer, config.max_decompressed_size)
+            .and_then(|buffer| Request::from_raw(&buffer).ok_or(400));
+        let mut req = match parsed {
+            Ok(req) => req,
+            Err(code) => {
+                let mut res = error_bodies
+                    .response(code, reason_phrase(code))
+                    .await
+                    .set_header("Connection", "close");
+                if code == 415 {
+                    res = res.set_header("Accept-Encoding", "gzip, deflate");
+                }
+                return respond(
+                    &mut stream,
+                    None,
+                    &res,
+                    false,
+                    started,
+                    &config,
+                    hook.as_ref(),
+                )
+                .await
+                .ok();
+            }
+        };
+        req.shutdown = Some(shutdown.clone());
+        req.secure = S::SECURE;
+        connection.begin_request();
+        req.connection = connection.clone();
+        let mut keep_alive = config.keep_alive && wants_keep_alive(&req);
+        let http_1_0 = req.version != "HTTP/1.1";
+        let head_only = req.method() == "HEAD";
+        // The handler consumes the request; keep a copy only for the hook
+        let reported = hook.is_some().then(|| req.clone());
+
+        let res = if config.cancel_on_disconnect {
+            let method = req.method.clone();
+            let path = req.path.clone();
+            let handled = handler(req);
+            tokio::pin!(handled);
+
+            // Watch the read side while the handler runs; EOF means the client left
+            let mut probe = [0; 512];
+            loop {
+                tokio::select! {
+                    res = &mut handled => break res,
+                    read = stream.read(&mut probe) => match read {
+                        Ok(0) | Err(_) => {
+                            println!("⚠️  [Server] client disconnected: {} {}", method, path);
+                            return None;
+                        }
+                        // Early bytes of the next request, or data for a hijacked connection
+                        // Past the request size limit they are dropped, so the
+                        // connection can't be reused
+                        Ok(n) => {
+                            if buffered.len() + n <= config.max_request_size {
+                                buffered.extend_from_slice(&probe[..n]);
+                            } else {
+                                keep_alive = false;
+                            }
+                        }
+                    },
                 }
+            }
+        } else {
+            handler(req).await
+        };
 
-                if let Some(req) = crate::http::request::Request::from_raw(&buffer[..n]) {
-                    let res: crate::http::response::Response = handler(req).await;
-                    let _ = stream.write_all(res.build().as_bytes()).await;
+        if let Some(sender) = res.hijack.as_ref().and_then(|slot| slot.take()) {
+            match stream.into_tcp() {
+                Ok(stream) => {
+                    if let (Some(hook), Some(req)) = (&hook, &reported) {
+                        hook(req, &res, started.elapsed(), 0);
+                    }
+                    let _ = sender.send(Connection { stream, buffered });
+                    return Some(());
                 }
+                // Dropping the sender fails the handler's `Hijack`
+                Err(kept) => stream = kept,
+            }
+        }
+        // A handler may close the connection by sending `Connection: close`
+        keep_alive &= !shutdown.is_cancelled() && !has_token(res.header("Connection"), "close");
+        let res = if !keep_alive {
+            res.set_header("Connection", "close")
+        } else if http_1_0 {
+            res.set_header("Connection", "keep-alive")
+        } else {
+            res
+        };
+        respond(
+            &mut stream,
+            reported.as_ref(),
+            &res,
+            head_only,
+            started,
+            &config,
+            hook.as_ref(),
+        )
+        .await
+        .ok()?;
+        if !keep_alive {
+            return Some(());
+        }
+    }
+}
+
+/// Whether the client lets the connection stay open after this request
+fn wants_keep_alive(req: &Request) -> bool {
+    let connection = req.header("Connection");
+    match req.version.as_str() {
+        "HTTP/1.1" => !has_token(connection, "close"),
+        _ => has_token(connection, "keep-alive"),
+    }
+}
+
+/// Whether a `Connection` header value lists `option`
+fn has_token(connection: Option<&String>, option: &str) -> bool {
+    connection.is_some_and(|value| {
+        value
+            .split(',')
+            .any(|token| token.trim().eq_ignore_ascii_case(option))
+    })
+}
+
+/// Write the response, then report it to the completion hook if there is one
+/// `req` is `None` for responses to requests that could not be parsed, and
+/// `head_only` leaves out the body, for HEAD requests
+async fn respond(
+    stream: &mut (dyn AsyncWrite + Unpin + Send),
+    req: Option<&Request>,
+    res: &Response,
+    head_only: bool,
+    started: Instant,
+    config: &ServerConfig,
+    hook: Option<&ResponseHook>,
+) -> tokio::io::Result<()> {
+    // A response that can't be serialized correctly is never written as is
+    let fallback;
+    let res = match res.invalid_part() {
+        Some(problem) => {
+            eprintln!("❌ [Server] invalid response ({}), sending 500", problem);
+            fallback = Response::from_status(500);
+            &fallback
+        }
+        None => res,
+    };
+    let mut writer = CountingWriter { stream, written: 0 };
+    let result = if head_only {
+        // Same head as the GET response would have, but never a body; a HEAD
+        // route without a body may announce the length itself
+        let length = match res.content_length() {
+            Some(0) => res
+                .header("Content-Length")
+                .and_then(|length| length.trim().parse().ok())
+                .or(Some(0)),
+            length => length,
+        };
+        writer.write_all(res.head(length).as_bytes()).await
+    } else {
+        write_response(&mut writer, res, config).await
+    };
+    if let Some(hook) = hook {
+        let unparsed = Request::default();
+        hook(
+            req.unwrap_or(&unparsed),
+            res,
+            started.elapsed(),
+            writer.written,
+        );
+    }
+    result
+}
+
+/// Counts the bytes that reach the socket, including a partial final write
+struct CountingWriter<'a> {
+    stream: &'a mut (dyn AsyncWrite + Unpin + Send),
+    written: u64,
+}
+
+impl CountingWriter<'_> {
+    async fn write_all(&mut self, mut buf: &[u8]) -> tokio::io::Result<()> {
+        while !buf.is_empty() {
+            let n = self.stream.write(buf).await?;
+            if n == 0 {
+                return Err(tokio::io::ErrorKind::WriteZero.into());
+            }
+            self.written += n as u64;
+            buf = &buf[n..];
+        }
+        Ok(())
+    }
+}
 
-                Some(())
-            });
+/// Wait up to `idle_timeout` for the first bytes of a connection
+/// `None` when the client closes, the wait times out or shutdown begins first
+async fn first_bytes<S: Transport>(
+    stream: &mut S,
+    config: &ServerConfig,
+    shutdown: &CancellationToken,
+) -> Option<Vec<u8>> {
+    let mut buffer = vec![
+        0;
+        config
+            .read_buffer_size
+            .clamp(1, config.max_request_size.max(1))
+    ];
+    let read = async {
+        match config.idle_timeout {
+            Some(limit) => tokio::time::timeout(limit, stream.read(&mut buffer))
+                .await
+                .ok(),
+            None => Some(stream.read(&mut buffer).await),
         }
+    };
+    let n = tokio::select! {
+        read = read => read?.ok()?,
+        _ = shutdown.cancelled() => return None,
+    };
+    if n == 0 {
+        return None;
+    }
+    buffer.truncate(n);
+    Some(buffer)
+}
+
+/// Write the response, streaming file bodies through a fixed-size buffer
+async fn write_response(
+    stream: &mut CountingWriter<'_>,
+    res: &Response,
+    config: &ServerConfig,
+) -> tokio::io::Result<()> {
+    if !res.allows_body() {
+        return stream.write_all(res.head(None).as_bytes()).await;
+    }
+    if let Some(body) = res.stream.as_ref().and_then(|body| body.take()) {
+        return write_chunked(stream, res, body).await;
+    }
+    if let Some(body) = &res.body {
+        stream
+            .write_all(res.head(Some(body.len() as u64)).as_bytes())
+            .await?;
+        return stream.write_all(body).await;
+    }
+    let Some(file) = &res.file else {
+        return stream.write_all(&res.build()).await;
+    };
+
+    // Open before writing the head so a vanished file can still become a 404
+    let mut source = match File::open(&file.path).await {
+        Ok(source) => source,
+        Err(_) => return stream.write_all(&Response::from_status(404).build()).await,
+    };
+    stream
+        .write_all(res.head(Some(file.len)).as_bytes())
+        .await?;
+
+    // Send exactly the advertised length even if the file changes underneath
+    let mut remaining = file.len;
+    let mut buffer = vec![0; config.file_chunk_size];
+    while remaining > 0 {
+        let want = buffer.len().min(remaining as usize);
+        let n = source.read(&mut buffer[..want]).await?;
+        if n == 0 {
+            return Err(tokio::io::ErrorKind::UnexpectedEof.into());
+        }
+        stream.write_all(&buffer[..n]).await?;
+        remaining -= n as u64;
+    }
+    Ok(())
+}
+
+/// Write a chunked body as frames arrive, then the terminator and any trailers
+async fn write_chunked(
+    stream: &mut CountingWriter<'_>,
+    res: &Response,
+    mut body: mpsc::Receiver<Frame>,
+) -> tokio::io::Result<()> {
+    stream.write_all(res.head(None).as_bytes()).await?;
+
+    let mut trailers = Vec::new();
+    while let Some(frame) = body.recv().await {
+        match frame {
+            Frame::Data(chunk) => {
+                let mut framed = format!("{:x}\r\n", chunk.len()).into_bytes();
+                framed.extend_from_slice(&chunk);
+                framed.extend_from_slice(b"\r\n");
+                stream.write_all(&framed).await?;
+            }
+            Frame::Trailers(fields) => {
+                trailers = fields;
+                break;
+            }
+            Frame::Abort => return Err(tokio::io::ErrorKind::ConnectionAborted.into()),
+        }
+    }
+
+    let mut end = String::from("0\r\n");
+    for (name, value) in trailers {
+        end.push_str(&format!("{}: {}\r\n", name, value));
     }
+    end.push_str("\r\n");
+    stream.write_all(end.as_bytes()).await
 }
@@ -1,2 +1,6 @@
+pub mod config;
+pub mod connection;
 pub mod listener;
 pub mod parser;
+#[cfg(feature = "tls")]
+pub mod tls;
@@ -0,0 +1,375 @@
+/// Reading raw HTTP requests off a connection
+use crate::server::config::ServerConfig;
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use std::io::Read;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Result of reading one request from a connection
+pub enum ReadOutcome {
+    /// The request head plus any `Content-Length` body that arrived
+    /// A chunked body arrives decoded, with `Transfer-Encoding` replaced by
+    /// the matching `Content-Length`
+    Request(Vec<u8>),
+    /// The peer closed the connection before sending anything
+    Closed,
+    /// The request exceeded `ServerConfig::max_request_size`
+    /// Carries 431 when the head did not fit or a header value exceeded
+    /// `max_header_value_size`, and 413 when the body did not fit or exceeded
+    /// `max_body_size`
+    TooLarge(u16),
+    /// The body framing is ambiguous or malformed (400), or uses a transfer
+    /// coding other than `chunked` (501)
+    Rejected(u16),
+    /// The request stopped arriving for longer than `read_timeout`
+    TimedOut,
+}
+
+/// How the end of the body is found, decided once the head is complete
+#[derive(Clone, Copy)]
+enum Framing {
+    /// Total request length: head, separator and `Content-Length` body
+    Length(usize),
+    /// Chunked body starting at this offset
+    Chunked(usize),
+}
+
+/// Read a request, starting with `read_buffer_size` bytes and doubling the
+/// buffer as more arrives, up to `max_request_size`
+///
+/// Reading stops once the head and its body are in. At EOF a head that
+/// never completed is returned for lenient parsing, while a body shorter than
+/// its `Content-Length` or an unfinished chunked body is rejected with 400.
+/// Bytes past `Content-Length` are never part of the body: they stay in the
+/// returned buffer for the next request, see `read_request_after`. Waiting longer
+/// than `idle_timeout` for the first byte counts as `Closed`, and longer than
+/// `read_timeout` for any later one as `TimedOut`. Requests carrying
+/// both `Transfer-Encoding` and `Content-Length` are rejected outright since
+/// the two can be read differently by a proxy in front (request smuggling),
+/// and `chunked` is only honored as the sole transfer coding.
+pub async fn read_request<R>(reader: &mut R, config: &ServerConfig) -> std::io::Result<ReadOutcome>
+where
+    R: AsyncRead + Unpin,
+{
+    read_request_after(reader, config, Vec::new()).await
+}
+
+/// Like `read_request`, starting from bytes already read past the previous
+/// request on the connection, so pipelined requests lose nothing
+///
+/// A request that is already complete in `carried` is returned without
+/// reading. The returned buffer may again extend past the request; split it
+/// at `expected_len` before parsing.
+pub async fn read_request_after<R>(
+    reader: &mut R,
+    config: &ServerConfig,
+    carried: Vec<u8>,
+) -> std::io::Result<ReadOutcome>
+where
+    R: AsyncRead + Unpin,
+{
+    let max = config.max_request_size.max(1);
+    let mut filled = carried.len();
+    let mut buffer = carried;
+    buffer.resize(filled.max(config.read_buffer_size.clamp(1, max)), 0);
+    let mut framing = None;
+
+    loop {
+        // The head is scanned until it is complete, then only the framing matters
+        if filled > 0 && framing.is_none() {
+            framing = match body_framing(&buffer[..filled]) {
+                Some(Ok(framing)) => Some(framing),
+                Some(Err(code)) => return Ok(ReadOutcome::Rejected(code)),
+                None => None,
+            };
+            if framing.is_some()
+                && longest_header_value(&buffer[..filled]) > config.max_header_value_size
+            {
+                return Ok(ReadOutcome::TooLarge(431));
+            }
+            // The declared length is checked before any of the body is read
+            if let (Some(Framing::Length(total)), Some((end, separator))) =
+                (framing, head_end(&buffer[..filled]))
+            {
+                if total - (end + separator) > config.max_body_size {
+                    return Ok(ReadOutcome::TooLarge(413));
+                }
+            }
+        }
+        match framing {
+            Some(Framing::Length(total)) if total > max => return Ok(ReadOutcome::TooLarge(413)),
+            Some(Framing::Length(total)) if filled >= total => break,
+            Some(Framing::Chunked(start)) => match decode_chunked(&buffer[start..filled]) {
+                Ok(Some((body, _))) if body.len() > config.max_body_size => {
+                    return Ok(ReadOutcome::TooLarge(413))
+                }
+                Ok(Some((body, used))) => {
+                    let rest = &buffer[start + used..filled];
+                    return Ok(ReadOutcome::Request(with_length(
+                        &buffer[..start],
+                        "Transfer-Encoding",
+                        &body,
+                        rest,
+                    )));
+                }
+                Ok(None) => {}
+                Err(()) => return Ok(ReadOutcome::Rejected(400)),
+            },
+            _ => {}
+        }
+
+        if filled >= buffer.len() {
+            if buffer.len() >= max {
+                let code = if framing.is_some() { 413 } else { 431 };
+                return Ok(ReadOutcome::TooLarge(code));
+            }
+            buffer.resize(buffer.len().saturating_mul(2).min(max), 0);
+        }
+
+        let limit = if filled == 0 {
+            config.idle_timeout
+        } else {
+            config.read_timeout
+        };
+        let read = reader.read(&mut buffer[filled..]);
+        let n = match limit {
+            Some(limit) => match tokio::time::timeout(limit, read).await {
+                Ok(n) => n?,
+                Err(_) if filled == 0 => return Ok(ReadOutcome::Closed),
+                Err(_) => return Ok(ReadOutcome::TimedOut),
+            },
+            None => read.await?,
+        };
+        if n == 0 {
+            if filled == 0 {
+                return Ok(ReadOutcome::Closed);
+            }
+            // A body cut short by the client is never handed to a handler
+            match framing {
+                Some(Framing::Chunked(_)) => return Ok(ReadOutcome::Rejected(400)),
+                Some(Framing::Length(total)) if filled < total => {
+                    return Ok(ReadOutcome::Rejected(400))
+                }
+                _ => {}
+            }
+            break;
+        }
+        filled += n;
+    }
+
+    buffer.truncate(filled);
+    Ok(ReadOutcome::Request(buffer))
+}
+
+/// Total request length once the head is complete: head, separator and body
+/// `None` while the head is incomplete or when its `Content-Length` is invalid
+pub fn expected_len(buffer: &[u8]) -> Option<usize> {
+    let (end, separator) = head_end(buffer)?;
+    let content_length = content_length(&buffer[..end]).ok()?;
+    Some(end + separator + content_length)
+}
+
+/// Body length declared by a head, 0 without `Content-Length`
+///
+/// Every value must be all digits. Repeats of one value, as separate lines
+/// or a comma-separated list, are collapsed; differing values are rejected
+/// with 400, since another server could pick a different one and read the
+/// rest of the body as a new request.
+fn content_length(head: &[u8]) -> Result<usize, u16> {
+    let mut length = None;
+    for value in header_values(head, "Content-Length").flat_map(|value| value.split(',')) {
+        let value = value.trim();
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(400);
+        }
+        let value = value.parse::<usize>().map_err(|_| 400u16)?;
+        if length.is_some_and(|length| length != value) {
+            return Err(400);
+        }
+        length = Some(value);
+    }
+    Ok(length.unwrap_or(0))
+}
+
+/// Decode a body sent with `Content-Encoding: gzip` or `deflate`
+///
+/// `buffer` must hold exactly one request, as split at `expected_len`. The
+/// decoded body replaces the original with a matching `Content-Length` and
+/// `Content-Encoding` removed, so handlers never see the compressed bytes.
+/// Fails with 415 for other codings, 400 for corrupt data and 413 once the
+/// decoded body grows past `limit`.
+pub fn decode_content(buffer: Vec<u8>, limit: usize) -> Result<Vec<u8>, u16> {
+    let Some((end, separator)) = head_end(&buffer) else {
+        return Ok(buffer);
+    };
+    let codings: Vec<String> = header_values(&buffer[..end], "Content-Encoding")
+        .flat_map(|value| value.split(',').map(|c| c.trim().to_ascii_lowercase()))
+        .filter(|coding| !coding.is_empty() && coding != "identity")
+        .collect();
+    if codings.is_empty() || buffer.len() == end + separator {
+        return Ok(buffer);
+    }
+
+    // Codings are listed in the order they were applied
+    let mut body = buffer[end + separator..].to_vec();
+    for coding in codings.iter().rev() {
+        body = match coding.as_str() {
+            "gzip" | "x-gzip" => inflate(MultiGzDecoder::new(&body[..]), limit)?,
+            "deflate" => match inflate(ZlibDecoder::new(&body[..]), limit) {
+                // Some clients send raw deflate data without the zlib wrapper
+                Err(400) => inflate(DeflateDecoder::new(&body[..]), limit)?,
+                decoded => decoded?,
+            },
+            _ => return Err(415),
+        };
+    }
+    Ok(with_length(
+        &buffer[..end + separator],
+        "Content-Encoding",
+        &body,
+        &[],
+    ))
+}
+
+/// Read a decoder to the end, stopping one byte past `limit`
+fn inflate(decoder: impl Read, limit: usize) -> Result<Vec<u8>, u16> {
+    let mut out = Vec::new();
+    decoder
+        .take(limit as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|_| 400u16)?;
+    if out.len() > limit {
+        return Err(413);
+    }
+    Ok(out)
+}
+
+/// Framing of a complete head, or the status to reject it with
+fn body_framing(buffer: &[u8]) -> Option<Result<Framing, u16>> {
+    let (end, separator) = head_end(buffer)?;
+    let head = &buffer[..end];
+    let codings: Vec<String> = header_values(head, "Transfer-Encoding")
+        .flat_map(|value| value.split(',').map(|c| c.trim().to_ascii_lowercase()))
+        .filter(|coding| !coding.is_empty())
+        .collect();
+    if codings.is_empty() {
+        return Some(content_length(head).map(|length| Framing::Length(end + separator + length)));
+    }
+
+    let chunked = codings.iter().filter(|c| *c == "chunked").count();
+    Some(if header_values(head, "Content-Length").next().is_some() {
+        Err(400)
+    } else if codings.last().is_some_and(|c| c != "chunked") {
+        // A request body that isn't chunked last has no knowable end
+        Err(if chunked > 0 { 400 } else { 501 })
+    } else if chunked > 1 {
+        Err(400)
+    } else if codings.len() > 1 {
+        Err(501)
+    } else {
+        Ok(Framing::Chunked(end + separator))
+    })
+}
+
+/// Trimmed values of every header line named `name` in a head
+fn header_values<'a>(head: &'a [u8], name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    head.split(|&b| b == b'\n').skip(1).filter_map(move |line| {
+        let line = std::str::from_utf8(line).ok()?;
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+/// Decode a chunked body, returning it with the number of bytes consumed
+/// `Ok(None)` means more input is needed; chunk extensions and trailers are
+/// discarded
+fn decode_chunked(input: &[u8]) -> Result<Option<(Vec<u8>, usize)>, ()> {
+    let mut body = Vec::new();
+    let mut at = 0;
+    loop {
+        let Some(line_len) = input[at..].windows(2).position(|w| w == b"\r\n") else {
+            return Ok(None);
+        };
+        let line = std::str::from_utf8(&input[at..at + line_len]).map_err(|_| ())?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(());
+        }
+        let size = usize::from_str_radix(size, 16).map_err(|_| ())?;
+        at += line_len + 2;
+
+        if size == 0 {
+            // Skip trailer fields up to the blank line that ends the body
+            loop {
+                let Some(len) = input[at..].windows(2).position(|w| w == b"\r\n") else {
+                    return Ok(None);
+                };
+                at += len + 2;
+                if len == 0 {
+                    return Ok(Some((body, at)));
+                }
+            }
+        }
+
+        let Some(chunk_end) = at.checked_add(size).filter(|&end| end <= input.len()) else {
+            return Ok(None);
+        };
+        match input.get(chunk_end..chunk_end + 2) {
+            Some(b"\r\n") => {}
+            Some(_) => return Err(()),
+            None => return Ok(None),
+        }
+        body.extend_from_slice(&input[at..chunk_end]);
+        at = chunk_end + 2;
+    }
+}
+
+/// Rebuild a request around a decoded body: the `dropped` header and any old
+/// `Content-Length` give way to the new length, and bytes past the body are
+/// kept after it
+fn with_length(head: &[u8], dropped: &str, body: &[u8], rest: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(head.len() + body.len() + rest.len() + 32);
+    for line in head.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let is_replaced = line.iter().position(|&b| b == b':').is_some_and(|colon| {
+            let name = line[..colon].trim_ascii();
+            name.eq_ignore_ascii_case(dropped.as_bytes())
+                || name.eq_ignore_ascii_case(b"Content-Length")
+        });
+        if !line.is_empty() && !is_replaced {
+            out.extend_from_slice(line);
+            out.extend_from_slice(b"\r\n");
+        }
+    }
+    out.extend_from_slice(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes());
+    out.extend_from_slice(body);
+    out.extend_from_slice(rest);
+    out
+}
+
+/// Length of the longest header value in a complete head
+fn longest_header_value(buffer: &[u8]) -> usize {
+    let Some((end, _)) = head_end(buffer) else {
+        return 0;
+    };
+    buffer[..end]
+        .split(|&b| b == b'\n')
+        .skip(1)
+        .filter_map(|line| {
+            let colon = line.iter().position(|&b| b == b':')?;
+            Some(line[colon + 1..].trim_ascii().len())
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Offset and length of the blank line ending the head, if it has arrived
+fn head_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n");
+    let lf = buffer.windows(2).position(|w| w == b"\n\n");
+    match (crlf, lf) {
+        (Some(crlf), Some(lf)) if lf < crlf => Some((lf, 2)),
+        (Some(crlf), _) => Some((crlf, 4)),
+        (None, Some(lf)) => Some((lf, 2)),
+        (None, None) => None,
+    }
+}
@@ -0,0 +1,43 @@
+/// TLS termination for HTTPS listeners (requires the `tls` feature)
+use std::{io, path::Path, sync::Arc};
+use tokio_rustls::rustls::{
+    self,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+};
+
+pub use tokio_rustls::rustls::ServerConfig;
+
+/// Shared rustls configuration for an HTTPS listener
+pub type TlsConfig = Arc<ServerConfig>;
+
+/// Build a TLS configuration from a PEM certificate chain and private key
+///
+/// The chain file holds the server certificate first, followed by any
+/// intermediates. Only HTTP/1.1 is offered through ALPN. Build a
+/// `ServerConfig` by hand for client certificates or other settings.
+///
+/// ```ignore
+/// let tls = tls::from_pem_files("certs/fullchain.pem", "certs/key.pem")?;
+/// app.listen_multi(&[Listener::Https(addr, tls)], |_| {}).await?;
+/// ```
+pub fn from_pem_files(
+    cert_chain: impl AsRef<Path>,
+    private_key: impl AsRef<Path>,
+) -> io::Result<TlsConfig> {
+    let invalid = |error: rustls::pki_types::pem::Error| {
+        io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+    };
+    let chain = CertificateDer::pem_file_iter(cert_chain)
+        .map_err(invalid)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid)?;
+    let key = PrivateKeyDer::from_pem_file(private_key).map_err(invalid)?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(chain, key))
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
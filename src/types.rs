@@ -1,8 +1,10 @@
 use crate::http::{request::Request, response::Response};
-use std::{future::Future, pin::Pin, sync::Arc};
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
 
 pub type Next = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync>;
 pub type Handler = Arc<
     dyn Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync,
 >;
 pub type BoxFuture = Pin<Box<dyn Future<Output = Response> + Send>>;
+pub type RewriteHook = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+pub type ResponseHook = Arc<dyn Fn(&Request, &Response, Duration, u64) + Send + Sync>;
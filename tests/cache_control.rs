@@ -0,0 +1,61 @@
+//! `Cache-Control` values written by `CacheControl`
+use expresso::prelude::*;
+
+fn header(directives: CacheControl) -> Option<String> {
+    Response::new()
+        .cache_control(directives)
+        .header("Cache-Control")
+        .cloned()
+}
+
+#[test]
+fn writes_common_combinations() {
+    assert_eq!(
+        header(CacheControl::new().public().max_age(3600)).as_deref(),
+        Some("public, max-age=3600")
+    );
+    assert_eq!(
+        header(CacheControl::immutable_asset()).as_deref(),
+        Some("public, max-age=31536000, immutable")
+    );
+    assert_eq!(
+        header(CacheControl::new().private().no_cache()).as_deref(),
+        Some("private, no-cache")
+    );
+    assert_eq!(
+        header(
+            CacheControl::new()
+                .max_age(60)
+                .s_maxage(600)
+                .stale_while_revalidate(30)
+                .must_revalidate()
+        )
+        .as_deref(),
+        Some("max-age=60, s-maxage=600, stale-while-revalidate=30, must-revalidate")
+    );
+    assert_eq!(header(CacheControl::new()), None);
+}
+
+#[test]
+fn resolves_conflicting_directives() {
+    // Nothing else applies to a response that is never stored
+    let never = CacheControl::new()
+        .public()
+        .max_age(60)
+        .immutable()
+        .no_store();
+    assert_eq!(never.to_string(), "no-store");
+
+    // The last of public/private wins, and shared caches never see private responses
+    let private = CacheControl::new()
+        .public()
+        .private()
+        .max_age(60)
+        .s_maxage(600);
+    assert_eq!(private.to_string(), "private, max-age=60");
+
+    // Immutable needs a freshness lifetime and contradicts no-cache
+    assert_eq!(CacheControl::new().immutable().to_string(), "");
+    let revalidated = CacheControl::new().max_age(60).no_cache().immutable();
+    assert_eq!(revalidated.to_string(), "no-cache, max-age=60");
+}
@@ -0,0 +1,86 @@
+//! Gzip response compression with `compression::gzip`
+use expresso::{middlewares::compression::gzip, prelude::*, types::BoxFuture};
+use flate2::read::GzDecoder;
+use std::{io::Read, sync::Arc};
+
+/// Run `gzip()` on a GET with `accept_encoding` in front of `handler`'s response
+async fn run(accept_encoding: Option<&str>, handler: fn() -> Response) -> Response {
+    let mut raw = String::from("GET /data HTTP/1.1\r\nHost: test\r\n");
+    if let Some(value) = accept_encoding {
+        raw.push_str(&format!("Accept-Encoding: {}\r\n", value));
+    }
+    raw.push_str("\r\n");
+    let req = Request::from_raw(raw.as_bytes()).unwrap();
+    let next: Next = Arc::new(move || -> BoxFuture { Box::pin(async move { handler() }) });
+    gzip()(req, Response::new(), next).await
+}
+
+fn large_json() -> Response {
+    let items = vec![r#"{"id":1,"name":"expresso","tags":["fast","small"]}"#; 200];
+    Response::new().json(&format!("[{}]", items.join(",")))
+}
+
+/// Build the response as written and split it into head lines and body bytes
+fn written(res: &Response) -> (Vec<String>, Vec<u8>) {
+    let raw = res.build();
+    let end = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8(raw[..end].to_vec()).unwrap();
+    let lines = head.split("\r\n").map(str::to_string).collect();
+    (lines, raw[end + 4..].to_vec())
+}
+
+#[tokio::test]
+async fn compressible_bodies_shrink_for_gzip_clients() {
+    let original = large_json().body_text().unwrap().to_string();
+    let res = run(Some("br;q=1.0, gzip;q=0.8"), large_json).await;
+    assert_eq!(
+        res.header("Content-Encoding").map(String::as_str),
+        Some("gzip")
+    );
+    assert_eq!(res.header_all("Vary"), ["Accept-Encoding"]);
+
+    let (head, body) = written(&res);
+    assert!(body.len() < original.len() / 4, "{} bytes", body.len());
+    assert!(head.contains(&format!("Content-Length: {}", body.len())));
+    assert!(head.contains(&"Content-Type: application/json; charset=utf-8".to_string()));
+
+    let mut decoded = String::new();
+    GzDecoder::new(&body[..])
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, original);
+}
+
+#[tokio::test]
+async fn other_clients_get_the_original_body() {
+    let original = large_json().body_text().unwrap().to_string();
+    for accept_encoding in [None, Some("br, deflate"), Some("gzip;q=0, *")] {
+        let res = run(accept_encoding, large_json).await;
+        assert!(
+            res.header("Content-Encoding").is_none(),
+            "{:?}",
+            accept_encoding
+        );
+        assert_eq!(res.body_text(), Some(original.as_str()));
+        assert_eq!(res.header_all("Vary"), ["Accept-Encoding"]);
+    }
+}
+
+#[tokio::test]
+async fn empty_and_encoded_bodies_are_left_alone() {
+    let res = run(Some("gzip"), || Response::new().status(204)).await;
+    assert!(res.header("Content-Encoding").is_none());
+    assert!(res.header("Vary").is_none());
+
+    let res = run(Some("gzip"), || {
+        Response::new()
+            .set_header("Content-Encoding", "br")
+            .send_bytes(vec![b'x'; 4096])
+    })
+    .await;
+    assert_eq!(
+        res.header("Content-Encoding").map(String::as_str),
+        Some("br")
+    );
+    assert_eq!(written(&res).1, vec![b'x'; 4096]);
+}
@@ -0,0 +1,45 @@
+//! Setting cookies on responses and reading them from requests
+use expresso::prelude::*;
+
+#[test]
+fn each_cookie_gets_its_own_set_cookie_line() {
+    let built = Response::new()
+        .set_cookie("theme", "dark")
+        .set_cookie_opts(
+            CookieOptions::new("session", "abc=123")
+                .path("/")
+                .domain("example.com")
+                .max_age(3600)
+                .http_only()
+                .same_site(SameSite::None),
+        )
+        .build();
+    let built = String::from_utf8(built).unwrap();
+    let cookies: Vec<_> = built
+        .split("\r\n")
+        .filter_map(|line| line.strip_prefix("Set-Cookie: "))
+        .collect();
+    assert_eq!(
+        cookies,
+        [
+            "theme=dark",
+            "session=abc=123; Path=/; Domain=example.com; Max-Age=3600; HttpOnly; Secure; SameSite=None",
+        ]
+    );
+}
+
+#[test]
+fn cookies_are_read_from_the_request_header() {
+    let raw = b"GET / HTTP/1.1\r\nHost: test\r\nCookie: theme=dark; session=abc=123; empty=; theme=light\r\n\r\n";
+    let req = Request::from_raw(raw).unwrap();
+    assert_eq!(req.cookie("theme"), Some("dark"));
+    assert_eq!(req.cookie("session"), Some("abc=123"));
+    assert_eq!(req.cookie("empty"), Some(""));
+    assert_eq!(req.cookie("missing"), None);
+}
+
+#[test]
+fn separators_cannot_inject_attributes() {
+    let cookie = CookieOptions::new("id", "1; Domain=evil.test\r\nX-Evil: 1").to_string();
+    assert_eq!(cookie, "id=1 Domain=evil.testX-Evil: 1");
+}
@@ -0,0 +1,170 @@
+//! CORS headers and preflight answers from `CorsConfig`
+use expresso::{middlewares::cors::CorsConfig, prelude::*};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Run `middleware` on `raw`, reporting whether it called `next`
+async fn run(
+    middleware: impl Fn(Request, Response, Next) -> expresso::types::BoxFuture,
+    raw: &[u8],
+) -> (Response, bool) {
+    let reached = Arc::new(AtomicBool::new(false));
+    let flag = reached.clone();
+    let next: Next = Arc::new(move || {
+        flag.store(true, Ordering::SeqCst);
+        Box::pin(async { Response::new().status(200).send("handled") })
+    });
+    let req = Request::from_raw(raw).unwrap();
+    let res = middleware(req, Response::new(), next).await;
+    (res, reached.load(Ordering::SeqCst))
+}
+
+const PREFLIGHT: &[u8] = b"OPTIONS /api HTTP/1.1\r\nHost: api.test\r\n\
+Origin: https://app.test\r\nAccess-Control-Request-Method: PUT\r\n\r\n";
+
+#[tokio::test]
+async fn preflight_is_answered_with_the_configured_headers() {
+    let cors = CorsConfig::new()
+        .origins(vec!["https://app.test".to_string()])
+        .methods(vec!["GET".to_string(), "PUT".to_string()])
+        .headers(vec!["X-Token".to_string()])
+        .preflight(true)
+        .build();
+
+    let (res, reached) = run(&cors, PREFLIGHT).await;
+    assert!(!reached);
+    assert_eq!(res.status_code, 204);
+    let written = String::from_utf8(res.build()).unwrap();
+    assert!(!written.contains("Content-Length"), "{}", written);
+    assert!(written.ends_with("\r\n\r\n"));
+    assert_eq!(
+        res.header("Access-Control-Allow-Origin")
+            .map(String::as_str),
+        Some("https://app.test")
+    );
+    assert_eq!(
+        res.header("Access-Control-Allow-Methods")
+            .map(String::as_str),
+        Some("GET, PUT")
+    );
+    assert_eq!(
+        res.header("Access-Control-Allow-Headers")
+            .map(String::as_str),
+        Some("X-Token")
+    );
+
+    // Other methods still reach the handler
+    let (res, reached) = run(
+        &cors,
+        b"GET /api HTTP/1.1\r\nHost: api.test\r\nOrigin: https://app.test\r\n\r\n",
+    )
+    .await;
+    assert!(reached);
+    assert_eq!(res.status_code, 200);
+    assert!(res.header("Access-Control-Allow-Origin").is_some());
+}
+
+#[tokio::test]
+async fn options_reaches_the_handler_unless_preflight_is_enabled() {
+    let (res, reached) = run(&CorsConfig::new().build(), PREFLIGHT).await;
+    assert!(reached);
+    assert_eq!(res.status_code, 200);
+    assert_eq!(
+        res.header("Access-Control-Allow-Origin")
+            .map(String::as_str),
+        Some("*")
+    );
+}
+
+fn from_origin(origin: &str) -> Vec<u8> {
+    format!(
+        "GET /api HTTP/1.1\r\nHost: api.test\r\nOrigin: {}\r\n\r\n",
+        origin
+    )
+    .into_bytes()
+}
+
+fn two_origins() -> CorsConfig {
+    CorsConfig::new().origins(vec![
+        "https://app.test".to_string(),
+        "https://admin.test".to_string(),
+    ])
+}
+
+#[tokio::test]
+async fn an_allowed_origin_is_echoed_alone() {
+    let (res, _) = run(&two_origins().build(), &from_origin("https://admin.test")).await;
+    assert_eq!(
+        res.header("Access-Control-Allow-Origin")
+            .map(String::as_str),
+        Some("https://admin.test")
+    );
+    assert_eq!(res.header_all("Vary"), ["Origin"]);
+}
+
+#[tokio::test]
+async fn other_origins_get_no_cors_headers() {
+    let (res, reached) = run(&two_origins().build(), &from_origin("https://evil.test")).await;
+    assert!(reached);
+    assert_eq!(res.status_code, 200);
+    assert!(res.header("Access-Control-Allow-Origin").is_none());
+    assert!(res.header("Access-Control-Allow-Methods").is_none());
+    assert_eq!(res.header_all("Vary"), ["Origin"]);
+}
+
+#[tokio::test]
+async fn a_wildcard_list_allows_every_origin() {
+    let (res, _) = run(&CorsConfig::new().build(), &from_origin("https://any.test")).await;
+    assert_eq!(
+        res.header("Access-Control-Allow-Origin")
+            .map(String::as_str),
+        Some("*")
+    );
+    assert!(res.header("Vary").is_none());
+}
+
+#[tokio::test]
+async fn credentials_echo_the_origin_instead_of_a_wildcard() {
+    let cors = CorsConfig::new()
+        .credentials(true)
+        .expose_headers(vec!["X-Request-Id".to_string()])
+        .build();
+    let (res, _) = run(&cors, &from_origin("https://app.test")).await;
+    assert_eq!(
+        res.header("Access-Control-Allow-Origin")
+            .map(String::as_str),
+        Some("https://app.test")
+    );
+    assert_eq!(
+        res.header("Access-Control-Allow-Credentials")
+            .map(String::as_str),
+        Some("true")
+    );
+    assert_eq!(
+        res.header("Access-Control-Expose-Headers")
+            .map(String::as_str),
+        Some("X-Request-Id")
+    );
+    assert_eq!(res.header_all("Vary"), ["Origin"]);
+
+    // Without an Origin there is nothing to echo
+    let (res, _) = run(&cors, b"GET /api HTTP/1.1\r\nHost: api.test\r\n\r\n").await;
+    assert!(res.header("Access-Control-Allow-Origin").is_none());
+    assert!(res.header("Access-Control-Allow-Credentials").is_none());
+}
+
+#[tokio::test]
+async fn max_age_is_sent_with_preflight_answers_only() {
+    let cors = CorsConfig::new().max_age(600).preflight(true).build();
+    let (res, _) = run(&cors, PREFLIGHT).await;
+    assert_eq!(
+        res.header("Access-Control-Max-Age").map(String::as_str),
+        Some("600")
+    );
+    assert!(res.header("Access-Control-Allow-Credentials").is_none());
+
+    let (res, _) = run(&cors, &from_origin("https://app.test")).await;
+    assert!(res.header("Access-Control-Max-Age").is_none());
+}
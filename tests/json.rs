@@ -0,0 +1,156 @@
+//! Typed JSON request bodies with `Request::json`
+#![cfg(feature = "serde")]
+use expresso::{http::json::JsonError, prelude::*, server::config::JsonLimits};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct NewUser {
+    name: String,
+    age: u32,
+}
+
+fn request(content_type: Option<&str>, body: &str) -> Request {
+    let content_type = content_type
+        .map(|value| format!("Content-Type: {}\r\n", value))
+        .unwrap_or_default();
+    let raw = format!(
+        "POST /users HTTP/1.1\r\n{}Content-Length: {}\r\n\r\n{}",
+        content_type,
+        body.len(),
+        body
+    );
+    Request::from_raw(raw.as_bytes()).unwrap()
+}
+
+#[test]
+fn deserializes_into_a_struct() {
+    let req = request(
+        Some("application/json; charset=utf-8"),
+        r#"{"name":"Ada","age":36}"#,
+    );
+    let user: NewUser = req.json().unwrap();
+    assert_eq!(
+        user,
+        NewUser {
+            name: "Ada".to_string(),
+            age: 36
+        }
+    );
+    // Structured syntax suffixes are JSON too
+    let req = request(
+        Some("application/merge-patch+json"),
+        r#"{"name":"Ada","age":37}"#,
+    );
+    assert_eq!(req.json::<NewUser>().unwrap().age, 37);
+}
+
+#[test]
+fn reports_a_missing_body() {
+    let req = request(Some("application/json"), "");
+    assert!(matches!(req.json::<NewUser>(), Err(JsonError::MissingBody)));
+}
+
+#[test]
+fn reports_the_wrong_content_type() {
+    let body = r#"{"name":"Ada","age":36}"#;
+    match request(Some("text/plain"), body).json::<NewUser>() {
+        Err(JsonError::WrongContentType { expected, found }) => {
+            assert_eq!(expected, "application/json");
+            assert_eq!(found.as_deref(), Some("text/plain"));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    let missing = request(None, body).json::<NewUser>().unwrap_err();
+    assert_eq!(
+        missing.to_string(),
+        "expected Content-Type application/json, got none"
+    );
+    assert_eq!(missing.into_response().status_code, 415);
+
+    // The expected type can be overridden, or the check skipped
+    let req = request(Some("text/plain"), body);
+    let lenient = JsonLimits::new().content_type("*/*");
+    assert!(req.json_with_limits::<NewUser>(lenient).is_ok());
+    let req = request(Some("application/x-user"), body);
+    let custom = JsonLimits::new().content_type("application/x-user");
+    assert!(req.json_with_limits::<NewUser>(custom).is_ok());
+}
+
+#[test]
+fn reports_parse_failures_with_a_message() {
+    let req = request(Some("application/json"), r#"{"name":"Ada","age":"old"}"#);
+    match req.json::<NewUser>() {
+        Err(JsonError::Invalid { path, message, .. }) => {
+            assert_eq!(path, "age");
+            assert!(message.contains("expected u32"), "{}", message);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Profile {
+    user: Tagged,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Tagged {
+    tags: Vec<String>,
+}
+
+#[test]
+fn type_mismatches_report_the_field_path_and_offset() {
+    let body = r#"{"user": {"tags": ["a", "b", 3]}}"#;
+    match request(Some("application/json"), body).json::<Profile>() {
+        Err(JsonError::Invalid {
+            path,
+            message,
+            line,
+            column,
+            offset,
+        }) => {
+            assert_eq!(path, "user.tags[2]");
+            assert!(message.contains("expected a string"), "{}", message);
+            assert_eq!((line, column), (1, 30));
+            // The offset points at the offending value
+            assert_eq!(offset, Some(29));
+            assert!(body[29..].starts_with("3]"));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn nesting_is_limited_to_max_depth() {
+    type Nested = Vec<Vec<Vec<u32>>>;
+    let limits = || JsonLimits::new().max_depth(3);
+
+    // Exactly at the limit parses
+    let req = request(Some("application/json"), "[[[1, 2]], [[3]]]");
+    let nested: Nested = req.json_with_limits(limits()).unwrap();
+    assert_eq!(nested, vec![vec![vec![1, 2]], vec![vec![3]]]);
+
+    // One level deeper is rejected before serde sees it
+    let req = request(Some("application/json"), "[[[1]], [[[2]]]]");
+    match req.json_with_limits::<Vec<Nested>>(limits()) {
+        Err(error @ JsonError::TooDeep { .. }) => {
+            assert!(matches!(
+                error,
+                JsonError::TooDeep {
+                    limit: 3,
+                    offset: 10
+                }
+            ));
+            assert_eq!(error.to_string(), "nesting exceeds depth 3");
+            assert_eq!(error.into_response().status_code, 422);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Brackets inside strings do not count
+    let req = request(Some("application/json"), r#"[["[[[\"[["]]"#);
+    let strings: Vec<Vec<String>> = req.json_with_limits(limits()).unwrap();
+    assert_eq!(strings, vec![vec![r#"[[["[["#.to_string()]]);
+}
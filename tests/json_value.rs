@@ -0,0 +1,40 @@
+//! JSON building without serde: escaping and number formatting
+use expresso::{
+    http::json_value::{escape, JsonValue},
+    prelude::*,
+};
+
+#[test]
+fn escapes_quotes_backslashes_and_control_characters() {
+    assert_eq!(escape(r#"say "hi""#), r#"say \"hi\""#);
+    assert_eq!(escape(r"C:\temp"), r"C:\\temp");
+    assert_eq!(escape("a\nb\tc\r\u{0}\u{1f}"), r"a\nb\tc\r\u0000\u001f");
+    assert_eq!(escape("café ☕"), "café ☕");
+}
+
+#[test]
+fn writes_compact_json() {
+    let value = JsonValue::object(&[
+        ("name", r#"Bob "\" <script>"#.into()),
+        ("age", 42.into()),
+        ("score", 0.5.into()),
+        ("ratio", f64::NAN.into()),
+        ("admin", false.into()),
+        ("nick", None::<&str>.into()),
+        ("tags", vec!["a", "b\n"].into()),
+    ]);
+    assert_eq!(
+        value.to_string(),
+        r#"{"name":"Bob \"\\\" <script>","age":42,"score":0.5,"ratio":null,"admin":false,"nick":null,"tags":["a","b\n"]}"#
+    );
+}
+
+#[test]
+fn json_object_sets_body_and_content_type() {
+    let res = Response::new().json_object(&[("key\"", "value".into())]);
+    assert_eq!(res.body_text(), Some(r#"{"key\"":"value"}"#));
+    assert_eq!(
+        res.header("Content-Type").map(String::as_str),
+        Some("application/json; charset=utf-8")
+    );
+}
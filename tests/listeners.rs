@@ -0,0 +1,153 @@
+//! Serving one app on several listeners with `listen_multi`, and over IPv6
+use expresso::{prelude::*, server::listener::Listener};
+use std::net::SocketAddr;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+    sync::oneshot,
+};
+
+async fn app() -> Expresso {
+    let app = Expresso::new();
+    app.get(
+        "/scheme",
+        (|req: Request, res: Response, _next: Next| async move {
+            res.send(if req.is_secure() { "https" } else { "http" })
+        },),
+    )
+    .await;
+    app
+}
+
+/// Start `listeners` and wait until all of them accept connections
+async fn start(app: Expresso, listeners: Vec<Listener>) -> Vec<SocketAddr> {
+    let (ready, bound) = oneshot::channel();
+    tokio::spawn(async move {
+        app.listen_multi(&listeners, move |addrs| {
+            let _ = ready.send(addrs);
+        })
+        .await
+    });
+    bound.await.expect("listeners failed to bind")
+}
+
+async fn get_scheme<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S) -> String {
+    stream
+        .write_all(b"GET /scheme HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = Vec::new();
+    // A TLS peer may close without close_notify once the response is sent
+    let _ = stream.read_to_end(&mut response).await;
+    let response = String::from_utf8(response).unwrap();
+    response.split_once("\r\n\r\n").unwrap().1.to_string()
+}
+
+fn ephemeral() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 0))
+}
+
+#[tokio::test]
+async fn serves_the_same_routes_on_every_listener() {
+    let addrs = start(
+        app().await,
+        vec![Listener::Http(ephemeral()), Listener::Http(ephemeral())],
+    )
+    .await;
+    assert_eq!(addrs.len(), 2);
+    assert_ne!(addrs[0], addrs[1]);
+    for addr in addrs {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        assert_eq!(get_scheme(stream).await, "http");
+    }
+}
+
+#[tokio::test]
+async fn a_failed_bind_closes_the_other_listeners() {
+    let taken = std::net::TcpListener::bind(ephemeral()).unwrap();
+    let taken = taken.local_addr().unwrap();
+    let (ready, bound) = oneshot::channel();
+
+    let result = app()
+        .await
+        .listen_multi(
+            &[Listener::Http(ephemeral()), Listener::Http(taken)],
+            move |addrs| {
+                let _ = ready.send(addrs);
+            },
+        )
+        .await;
+    assert!(result.is_err());
+    assert!(bound.await.is_err(), "callback ran despite the failed bind");
+}
+
+#[tokio::test]
+async fn serves_over_ipv6_loopback() {
+    // Hosts without IPv6 can't bind ::1 at all
+    if std::net::TcpListener::bind("[::1]:0").is_err() {
+        eprintln!("skipping: IPv6 is unavailable");
+        return;
+    }
+    let (ready, bound) = oneshot::channel();
+    let app = app().await;
+    tokio::spawn(async move {
+        app.listen_with_addr("[::1]:0", move |addr| {
+            let _ = ready.send(addr);
+        })
+        .await
+    });
+    let addr = bound.await.expect("server failed to bind");
+    assert!(addr.is_ipv6());
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    assert!(stream.local_addr().unwrap().is_ipv6());
+    assert_eq!(get_scheme(stream).await, "http");
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn terminates_tls_next_to_plain_http() {
+    use expresso::server::tls;
+    use std::sync::Arc;
+    use tokio_rustls::{
+        rustls::{
+            pki_types::{pem::PemObject, CertificateDer, ServerName},
+            ClientConfig, RootCertStore,
+        },
+        TlsConnector,
+    };
+
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let config = tls::from_pem_files(
+        format!("{}/localhost.pem", fixtures),
+        format!("{}/localhost-key.pem", fixtures),
+    )
+    .unwrap();
+    let addrs = start(
+        app().await,
+        vec![
+            Listener::Http(ephemeral()),
+            Listener::Https(ephemeral(), config),
+        ],
+    )
+    .await;
+
+    let plain = TcpStream::connect(addrs[0]).await.unwrap();
+    assert_eq!(get_scheme(plain).await, "http");
+
+    let mut roots = RootCertStore::empty();
+    let cert = CertificateDer::from_pem_file(format!("{}/localhost.pem", fixtures)).unwrap();
+    roots.add(cert).unwrap();
+    let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+    let client = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let tcp = TcpStream::connect(addrs[1]).await.unwrap();
+    let secure = TlsConnector::from(Arc::new(client))
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await
+        .unwrap();
+    assert_eq!(get_scheme(secure).await, "https");
+}
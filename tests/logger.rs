@@ -0,0 +1,93 @@
+//! What `body_logger` prints: masked headers and fields, truncated bodies
+use expresso::{http::headers::Headers, middlewares::logger::BodyLoggerConfig, prelude::*};
+
+fn render(config: &BodyLoggerConfig, body: &str, content_type: Option<&str>) -> String {
+    config.render(
+        Some(body.as_bytes()),
+        content_type.map(String::from).as_ref(),
+    )
+}
+
+#[test]
+fn redacted_headers_are_masked_by_name() {
+    let mut headers = Headers::new();
+    headers.append("Host", "example.com".to_string());
+    headers.append("authorization", "Bearer abc".to_string());
+    headers.append("Cookie", "session=1".to_string());
+
+    let rendered = BodyLoggerConfig::new().render_headers(&headers);
+    assert_eq!(
+        rendered,
+        "Host: example.com, Authorization: ***, Cookie: ***"
+    );
+
+    let custom = BodyLoggerConfig::new().redact(vec!["host".to_string()]);
+    assert_eq!(
+        custom.render_headers(&headers),
+        "Host: ***, Authorization: Bearer abc, Cookie: session=1"
+    );
+}
+
+#[test]
+fn redacted_fields_are_masked_in_json_and_forms() {
+    let config = BodyLoggerConfig::new();
+    let json = r#"{"user":"ada","Password": "hunter\"2","token":42,"nested":{"secret":null}}"#;
+    assert_eq!(
+        render(&config, json, Some("application/json")),
+        r#"{"user":"ada","Password": "***","token":***,"nested":{"secret":***}}"#
+    );
+
+    let form = "user=ada&password=hunter2&mytoken=kept&TOKEN=abc";
+    assert_eq!(
+        render(&config, form, Some("application/x-www-form-urlencoded")),
+        "user=ada&password=***&mytoken=kept&TOKEN=***"
+    );
+}
+
+#[test]
+fn bodies_are_truncated_after_redaction() {
+    let config = BodyLoggerConfig::new().max_len(12);
+    assert_eq!(
+        render(&config, "password=hunter2&x=1", None),
+        "password=***…"
+    );
+    // Truncation counts characters, not bytes
+    assert_eq!(
+        render(&config, "ééééééééééééé", Some("text/plain")),
+        "éééééééééééé…"
+    );
+    assert_eq!(render(&config, "short", Some("text/plain")), "short");
+}
+
+#[test]
+fn empty_and_binary_bodies_are_summarized() {
+    let config = BodyLoggerConfig::new();
+    assert_eq!(config.render(None, None), "(empty)");
+    assert_eq!(render(&config, "", None), "(empty)");
+    assert_eq!(render(&config, "\u{0}\u{1}", None), "(binary, 2 bytes)");
+    assert_eq!(
+        render(&config, "PNG", Some("image/png")),
+        "(binary, 3 bytes)"
+    );
+    assert_eq!(config.render(Some(b"ok\xff"), None), "(binary, 3 bytes)");
+}
+
+#[tokio::test]
+async fn binary_and_file_responses_are_logged_by_size() {
+    let config = BodyLoggerConfig::new();
+    let res = Response::new().send_bytes(vec![0x89, b'P', b'N', b'G']);
+    assert_eq!(config.render_response(&res), "(binary, 4 bytes)");
+    let res = Response::new()
+        .content_type("text/plain")
+        .send_static(b"hello");
+    assert_eq!(config.render_response(&res), "hello");
+
+    let path = std::env::temp_dir().join(format!("expresso-logger-{}.txt", std::process::id()));
+    std::fs::write(&path, "secret=1").unwrap();
+    let res = Response::new().send_file(&path).await;
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(config.render_response(&res), "(file, 8 bytes)");
+
+    let (res, _body) = Response::new().chunked();
+    assert_eq!(config.render_response(&res), "(streamed)");
+}
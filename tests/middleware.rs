@@ -0,0 +1,143 @@
+//! The global middleware chain and the middleware combinators
+use expresso::{
+    handler::ChainPosition,
+    middleware::{guard, when, when_query},
+    prelude::*,
+    types::BoxFuture,
+};
+use std::{
+    ops::ControlFlow,
+    sync::{Arc, Mutex},
+};
+
+async fn pass(_req: Request, _res: Response, next: Next) -> Response {
+    next().await
+}
+
+#[tokio::test]
+async fn describe_chain_lists_names_in_registration_order() {
+    let app = Expresso::new();
+    assert_eq!(app.middleware_count().await, 0);
+    app.use_named_middleware("auth", pass).await;
+    app.use_middleware(pass).await;
+    app.use_named_middleware("compression", pass).await;
+
+    assert_eq!(app.middleware_count().await, 3);
+    assert_eq!(
+        app.describe_chain().await,
+        ["auth", "<anonymous>", "compression"]
+    );
+}
+
+#[tokio::test]
+async fn builder_registers_named_middleware() {
+    let app = Expresso::builder()
+        .use_middleware(pass)
+        .use_named_middleware("logger", pass)
+        .build();
+    assert_eq!(app.describe_chain().await, ["<anonymous>", "logger"]);
+}
+
+/// Run `middleware` for a GET of `path` in front of a handler that sends "handled"
+async fn run(middleware: impl Fn(Request, Response, Next) -> BoxFuture, path: &str) -> Response {
+    let raw = format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", path);
+    let req = Request::from_raw(raw.as_bytes()).unwrap();
+    let next: Next =
+        Arc::new(|| -> BoxFuture { Box::pin(async { Response::new().send("handled") }) });
+    middleware(req, Response::new(), next).await
+}
+
+#[tokio::test]
+async fn when_runs_the_middleware_only_for_matching_requests() {
+    let tag = when(
+        |req: &Request| req.path().starts_with("/api/"),
+        |_req: Request, _res: Response, next: Next| async move { next().await.set_header("X-Api", "1") },
+    );
+
+    let res = run(&tag, "/api/x").await;
+    assert_eq!(res.header("X-Api").map(String::as_str), Some("1"));
+    assert_eq!(res.body_text(), Some("handled"));
+
+    let res = run(&tag, "/public/x").await;
+    assert!(res.header("X-Api").is_none());
+    assert_eq!(res.body_text(), Some("handled"));
+}
+
+#[tokio::test]
+async fn guard_breaks_with_its_response_or_continues_the_chain() {
+    let admin_only = guard(|req: Request, res: Response| async move {
+        if req.path().starts_with("/admin") {
+            ControlFlow::Break(res.status(403).send("Forbidden"))
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+
+    // Break answers without calling next
+    let res = run(&admin_only, "/admin/users").await;
+    assert_eq!(res.status_code, 403);
+    assert_eq!(res.body_text(), Some("Forbidden"));
+
+    let res = run(&admin_only, "/public").await;
+    assert_eq!(res.status_code, 200);
+    assert_eq!(res.body_text(), Some("handled"));
+}
+
+#[tokio::test]
+async fn when_query_dispatches_on_the_parameter_value() {
+    let edit = when_query(
+        "action",
+        "edit",
+        |_req: Request, res: Response, _next: Next| async move { res.send("editing") },
+    );
+
+    let res = run(&edit, "/posts?action=edit").await;
+    assert_eq!(res.status_code, 200);
+    assert_eq!(res.body_text(), Some("editing"));
+
+    let res = run(&edit, "/posts").await;
+    assert_eq!(res.status_code, 400);
+    assert!(res.body_text().unwrap().contains("`action`"));
+
+    let res = run(&edit, "/posts?action=delete").await;
+    assert_eq!(res.status_code, 404);
+}
+
+#[tokio::test]
+async fn chain_position_tracks_each_handler_of_a_route() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let step = || {
+        let seen = seen.clone();
+        move |req: Request, res: Response, next: Next| {
+            let seen = seen.clone();
+            async move {
+                let position = req.chain_position().unwrap();
+                seen.lock().unwrap().push(position);
+                if position.is_last() {
+                    res.send("done")
+                } else {
+                    next().await
+                }
+            }
+        }
+    };
+    let route = (step(), step(), step()).into_chained_handler();
+
+    let res = run(move |req, res, next| route(req, res, next), "/steps").await;
+    assert_eq!(res.body_text(), Some("done"));
+    let seen = seen.lock().unwrap();
+    assert_eq!(
+        *seen,
+        [0, 1, 2].map(|index| ChainPosition { index, len: 3 })
+    );
+    assert_eq!(
+        seen.iter()
+            .map(ChainPosition::remaining)
+            .collect::<Vec<_>>(),
+        [2, 1, 0]
+    );
+    assert_eq!(
+        seen.iter().map(ChainPosition::is_last).collect::<Vec<_>>(),
+        [false, false, true]
+    );
+}
@@ -0,0 +1,367 @@
+//! Request parsing edge cases for `Request::from_raw` and `read_request`
+use expresso::{
+    prelude::*,
+    server::{
+        config::ServerConfig,
+        parser::{expected_len, read_request, read_request_after, ReadOutcome},
+    },
+};
+
+async fn read(raw: &[u8]) -> ReadOutcome {
+    let mut reader = raw;
+    read_request(&mut reader, &ServerConfig::default())
+        .await
+        .unwrap()
+}
+
+#[test]
+fn accepts_a_well_formed_request_line() {
+    let req = Request::from_raw(b"GET /path?x=1 HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+    assert_eq!(req.method(), "GET");
+    assert_eq!(req.path(), "/path");
+    assert_eq!(req.version, "HTTP/1.1");
+}
+
+#[test]
+fn splits_the_body_at_the_blank_line() {
+    let req =
+        Request::from_raw(b"POST /p HTTP/1.1\r\nHost: a\r\nX-Y: 1\r\n\r\nline one\r\n\r\nline two")
+            .unwrap();
+    assert_eq!(req.header("X-Y").map(String::as_str), Some("1"));
+    assert_eq!(
+        req.body().map(String::as_str),
+        Some("line one\r\n\r\nline two")
+    );
+
+    // Bare LF separators are accepted too
+    let req = Request::from_raw(b"POST /p HTTP/1.1\nHost: a\n\nbody").unwrap();
+    assert_eq!(req.header("Host").map(String::as_str), Some("a"));
+    assert_eq!(req.body().map(String::as_str), Some("body"));
+}
+
+#[test]
+fn a_head_without_the_blank_line_has_no_body() {
+    for raw in [
+        &b"GET /p HTTP/1.1\r\nHost: a\r\nX-Y: 1"[..],
+        b"GET /p HTTP/1.1\r\nHost: a\r\nX-Y: 1\r\n",
+    ] {
+        let req = Request::from_raw(raw).unwrap();
+        assert_eq!(req.header("X-Y").map(String::as_str), Some("1"));
+        assert!(req.body().is_none_or(String::is_empty));
+    }
+}
+
+#[test]
+fn rejects_double_spaces_in_the_request_line() {
+    assert!(Request::from_raw(b"GET  /path HTTP/1.1\r\n\r\n").is_none());
+    assert!(Request::from_raw(b"GET /path  HTTP/1.1\r\n\r\n").is_none());
+}
+
+#[test]
+fn rejects_four_token_request_lines() {
+    assert!(Request::from_raw(b"GET /path HTTP/1.1 extra\r\n\r\n").is_none());
+}
+
+#[test]
+fn rejects_surrounding_and_non_space_whitespace() {
+    assert!(Request::from_raw(b" GET /path HTTP/1.1\r\n\r\n").is_none());
+    assert!(Request::from_raw(b"GET /path HTTP/1.1 \r\n\r\n").is_none());
+    assert!(Request::from_raw(b"GET\t/path HTTP/1.1\r\n\r\n").is_none());
+}
+
+#[tokio::test]
+async fn rejects_transfer_encoding_with_content_length() {
+    let raw =
+        b"POST / HTTP/1.1\r\nContent-Length: 4\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+    assert!(matches!(read(raw).await, ReadOutcome::Rejected(400)));
+}
+
+#[tokio::test]
+async fn rejects_content_lengths_that_are_not_all_digits() {
+    for value in [
+        "abc",
+        "5 5",
+        "-1",
+        "+5",
+        "0x10",
+        "",
+        "99999999999999999999999",
+    ] {
+        let raw = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\nhelloGET /next HTTP/1.1\r\n\r\n",
+            value
+        );
+        assert!(
+            matches!(read(raw.as_bytes()).await, ReadOutcome::Rejected(400)),
+            "{:?}",
+            value
+        );
+        assert_eq!(expected_len(raw.as_bytes()), None, "{:?}", value);
+    }
+}
+
+#[tokio::test]
+async fn rejects_conflicting_content_lengths_and_collapses_repeats() {
+    for lengths in [
+        "Content-Length: 5\r\nContent-Length: 50",
+        "Content-Length: 5, 50",
+    ] {
+        let raw = format!("POST / HTTP/1.1\r\n{}\r\n\r\nhello", lengths);
+        assert!(
+            matches!(read(raw.as_bytes()).await, ReadOutcome::Rejected(400)),
+            "{}",
+            lengths
+        );
+    }
+
+    for lengths in [
+        "Content-Length: 5\r\nContent-Length: 5",
+        "Content-Length: 5, 5",
+    ] {
+        let raw = format!("POST / HTTP/1.1\r\n{}\r\n\r\nhello", lengths);
+        let ReadOutcome::Request(buffer) = read(raw.as_bytes()).await else {
+            panic!("repeated length was rejected: {}", lengths);
+        };
+        let req = Request::from_raw(&buffer).unwrap();
+        assert_eq!(req.body().map(String::as_str), Some("hello"));
+    }
+}
+
+#[tokio::test]
+async fn rejects_transfer_codings_other_than_chunked() {
+    let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\nxyz";
+    assert!(matches!(read(raw).await, ReadOutcome::Rejected(501)));
+    // chunked must be the final coding
+    let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\nxyz";
+    assert!(matches!(read(raw).await, ReadOutcome::Rejected(400)));
+}
+
+#[tokio::test]
+async fn decodes_chunked_bodies() {
+    let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nX-Sum: 1\r\n\r\n";
+    let ReadOutcome::Request(buffer) = read(raw).await else {
+        panic!("chunked request was not read");
+    };
+    let req = Request::from_raw(&buffer).unwrap();
+    assert_eq!(req.body().map(String::as_str), Some("hello world"));
+    assert_eq!(req.header("Content-Length").map(String::as_str), Some("11"));
+    assert!(req.header("Transfer-Encoding").is_none());
+
+    let truncated = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel";
+    assert!(matches!(read(truncated).await, ReadOutcome::Rejected(400)));
+}
+
+#[tokio::test]
+async fn pipelined_requests_split_without_losing_bytes() {
+    let raw = b"POST /a HTTP/1.1\r\nContent-Length: 2\r\n\r\nhiGET /b HTTP/1.1\r\n\r\n";
+    let ReadOutcome::Request(mut first) = read(raw).await else {
+        panic!("first request was not read");
+    };
+    let carried = first.split_off(expected_len(&first).unwrap());
+    assert_eq!(Request::from_raw(&first).unwrap().body().unwrap(), "hi");
+
+    // The carried bytes hold the whole second request
+    let mut empty: &[u8] = &[];
+    let outcome = read_request_after(&mut empty, &ServerConfig::default(), carried)
+        .await
+        .unwrap();
+    let ReadOutcome::Request(second) = outcome else {
+        panic!("carried request was not returned");
+    };
+    assert_eq!(Request::from_raw(&second).unwrap().path(), "/b");
+}
+
+#[tokio::test]
+async fn bytes_past_content_length_start_the_next_request() {
+    let raw = b"POST /a HTTP/1.1\r\nContent-Length: 5\r\n\r\nhelloGET /b HTTP/1.1\r\nHost: x\r\n\r\ntrailing junk";
+    let ReadOutcome::Request(mut buffer) = read(raw).await else {
+        panic!("request was not read");
+    };
+    let carried = buffer.split_off(expected_len(&buffer).unwrap());
+    let req = Request::from_raw(&buffer).unwrap();
+    assert_eq!(req.body().map(String::as_str), Some("hello"));
+    assert!(carried.starts_with(b"GET /b HTTP/1.1\r\n"));
+    assert!(carried.ends_with(b"trailing junk"));
+}
+
+#[tokio::test]
+async fn bodies_shorter_than_content_length_are_rejected() {
+    let raw = b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nhello";
+    assert!(matches!(read(raw).await, ReadOutcome::Rejected(400)));
+    // A head cut short is still parsed leniently
+    let raw = b"GET / HTTP/1.1\r\nHost: x\r\n";
+    assert!(matches!(read(raw).await, ReadOutcome::Request(_)));
+}
+
+#[test]
+fn splits_the_query_from_the_path() {
+    let req = Request::from_raw(b"GET /search?q=rust&page=2 HTTP/1.1\r\n\r\n").unwrap();
+    assert_eq!(req.path(), "/search");
+    assert_eq!(req.query("q").map(String::as_str), Some("rust"));
+    assert_eq!(req.query("page").map(String::as_str), Some("2"));
+    assert_eq!(req.query_all().len(), 2);
+
+    let empty = Request::from_raw(b"GET /search? HTTP/1.1\r\n\r\n").unwrap();
+    assert_eq!(empty.path(), "/search");
+    assert!(empty.query_all().is_empty());
+}
+
+#[test]
+fn decodes_query_keys_and_values() {
+    let raw =
+        b"GET /p?flag&a=1&a=2&caf%C3%A9=cr%C3%A8me%20br%C3%BBl%C3%A9e&bad=%zz HTTP/1.1\r\n\r\n";
+    let req = Request::from_raw(raw).unwrap();
+    assert_eq!(req.query("flag").map(String::as_str), Some(""));
+    assert_eq!(req.query("a").map(String::as_str), Some("2"));
+    assert_eq!(req.query("café").map(String::as_str), Some("crème brûlée"));
+    // Malformed escapes are kept as sent
+    assert_eq!(req.query("bad").map(String::as_str), Some("%zz"));
+}
+
+fn form_request(content_type: &str, body: &str) -> Request {
+    let raw = format!(
+        "POST /login HTTP/1.1\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+        content_type,
+        body.len(),
+        body
+    );
+    Request::from_raw(raw.as_bytes()).unwrap()
+}
+
+#[test]
+fn parses_a_login_form() {
+    let req = form_request(
+        "application/x-www-form-urlencoded",
+        "username=ada&password=hunter2&remember=",
+    );
+    let form = req.form().unwrap();
+    assert_eq!(form.get("username").map(String::as_str), Some("ada"));
+    assert_eq!(form.get("password").map(String::as_str), Some("hunter2"));
+    assert_eq!(form.get("remember").map(String::as_str), Some(""));
+
+    let json = form_request("application/json", "{}");
+    assert!(json.form().is_none());
+}
+
+#[test]
+fn decodes_plus_and_escapes_in_form_fields() {
+    let req = form_request(
+        "Application/X-WWW-Form-Urlencoded; charset=UTF-8",
+        "full+name=Ada+Lovelace&note=1%2B1%3D2+%26+caf%C3%A9&flag",
+    );
+    let form = req.form().unwrap();
+    assert_eq!(
+        form.get("full name").map(String::as_str),
+        Some("Ada Lovelace")
+    );
+    assert_eq!(form.get("note").map(String::as_str), Some("1+1=2 & café"));
+    assert_eq!(form.get("flag").map(String::as_str), Some(""));
+}
+
+#[test]
+fn header_names_match_in_any_case() {
+    let sent = [
+        ("CONTENT-TYPE", "Content-Type"),
+        ("content-type", "CONTENT-TYPE"),
+        ("Content-Type", "content-type"),
+        ("x-custom", "X-Custom"),
+    ];
+    for (name, lookup) in sent {
+        let raw = format!("GET / HTTP/1.1\r\n{}: text/html\r\n\r\n", name);
+        let req = Request::from_raw(raw.as_bytes()).unwrap();
+        assert_eq!(
+            req.header(lookup).map(String::as_str),
+            Some("text/html"),
+            "{} looked up as {}",
+            name,
+            lookup
+        );
+    }
+}
+
+#[tokio::test]
+async fn bodies_over_max_body_size_are_rejected() {
+    let config = ServerConfig::default().max_body_size(8);
+    let read_with = |raw: &'static [u8]| {
+        let config = config.clone();
+        async move { read_request(&mut &raw[..], &config).await.unwrap() }
+    };
+
+    // The declared length alone is enough, before any body bytes arrive
+    let declared = read_with(b"POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\n").await;
+    assert!(matches!(declared, ReadOutcome::TooLarge(413)));
+    let chunked =
+        read_with(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n5\r\nworld\r\n0\r\n\r\n")
+            .await;
+    assert!(matches!(chunked, ReadOutcome::TooLarge(413)));
+
+    let fits = read_with(b"POST / HTTP/1.1\r\nContent-Length: 8\r\n\r\nexactly8").await;
+    let ReadOutcome::Request(buffer) = fits else {
+        panic!("a body within the limit was rejected");
+    };
+    let req = Request::from_raw(&buffer).unwrap();
+    assert_eq!(req.body().map(String::as_str), Some("exactly8"));
+}
+
+#[tokio::test]
+async fn a_head_that_never_finishes_times_out() {
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    let config = ServerConfig::default().read_timeout(Duration::from_millis(50));
+    // The client end stays open but sends only part of the head
+    let (mut client, mut server) = tokio::io::duplex(64);
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: te")
+        .await
+        .unwrap();
+
+    let outcome = read_request(&mut server, &config).await.unwrap();
+    assert!(matches!(outcome, ReadOutcome::TimedOut));
+    drop(client);
+}
+
+#[tokio::test]
+async fn timeouts_can_be_disabled() {
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    let config = ServerConfig::default()
+        .idle_timeout(Duration::from_millis(10))
+        .read_timeout(Duration::from_millis(10))
+        .idle_timeout(None)
+        .read_timeout(None);
+    assert_eq!((config.idle_timeout, config.read_timeout), (None, None));
+
+    // Neither a silent client nor a stalled head ends the read
+    let (_silent, mut server) = tokio::io::duplex(64);
+    let read = read_request(&mut server, &config);
+    assert!(tokio::time::timeout(Duration::from_millis(100), read)
+        .await
+        .is_err());
+
+    let (mut client, mut server) = tokio::io::duplex(64);
+    client.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+    let read = read_request(&mut server, &config);
+    assert!(tokio::time::timeout(Duration::from_millis(100), read)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn one_oversized_header_value_is_431() {
+    let config = ServerConfig::default().max_header_value_size(64);
+    let read_with = |value: String| {
+        let config = config.clone();
+        async move {
+            let raw = format!("GET / HTTP/1.1\r\nHost: a\r\nCookie: {}\r\n\r\n", value);
+            read_request(&mut raw.as_bytes(), &config).await.unwrap()
+        }
+    };
+
+    // The head as a whole is far below max_request_size
+    let oversized = read_with("x".repeat(65)).await;
+    assert!(matches!(oversized, ReadOutcome::TooLarge(431)));
+    let at_limit = read_with("x".repeat(64)).await;
+    assert!(matches!(at_limit, ReadOutcome::Request(_)));
+}
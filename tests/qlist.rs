@@ -0,0 +1,75 @@
+//! Weighted list parsing shared by the Accept-* headers, and language negotiation
+use expresso::{http::qlist, prelude::*};
+
+fn entries(list: &[(&str, f32)]) -> Vec<(String, f32)> {
+    list.iter().map(|(v, q)| (v.to_string(), *q)).collect()
+}
+
+#[test]
+fn sorts_by_weight_and_keeps_header_order_for_ties() {
+    assert_eq!(
+        qlist::parse("fr;q=0.9, en-US, de;q=0.9, en"),
+        entries(&[("en-US", 1.0), ("en", 1.0), ("fr", 0.9), ("de", 0.9)])
+    );
+}
+
+#[test]
+fn malformed_weights_default_to_one_and_are_clamped() {
+    assert_eq!(
+        qlist::parse("gzip;q=abc, br;q=7, identity;q=-1"),
+        entries(&[("gzip", 1.0), ("br", 1.0), ("identity", 0.0)])
+    );
+}
+
+#[test]
+fn keeps_media_type_parameters_and_skips_empty_entries() {
+    assert_eq!(
+        qlist::parse("text/html;level=1;q=0.5;ext=x, , */*;Q=0.1"),
+        entries(&[("text/html;level=1", 0.5), ("*/*", 0.1)])
+    );
+}
+
+#[test]
+fn round_trips_through_format() {
+    let header = "br, gzip;q=0.8, identity;q=0";
+    assert_eq!(qlist::format(&qlist::parse(header)), header);
+    assert_eq!(
+        qlist::parse(&qlist::format(&qlist::parse(header))),
+        qlist::parse(header)
+    );
+}
+
+fn with_languages(header: &str) -> Request {
+    let raw = format!("GET / HTTP/1.1\r\nAccept-Language: {}\r\n\r\n", header);
+    Request::from_raw(raw.as_bytes()).unwrap()
+}
+
+#[test]
+fn accept_languages_are_ordered_by_weight() {
+    let req = with_languages("fr;q=0.5, en-US, de;q=0.8, es");
+    assert_eq!(
+        req.accept_languages(),
+        entries(&[("en-US", 1.0), ("es", 1.0), ("de", 0.8), ("fr", 0.5)])
+    );
+    assert!(Request::from_raw(b"GET / HTTP/1.1\r\n\r\n")
+        .unwrap()
+        .accept_languages()
+        .is_empty());
+}
+
+#[test]
+fn preferred_language_matches_exactly_then_by_primary_subtag() {
+    let req = with_languages("de-CH, fr;q=0.9, en;q=0.1");
+    assert_eq!(req.preferred_language(&["en", "fr", "de"]), Some("de"));
+    assert_eq!(req.preferred_language(&["en", "fr"]), Some("fr"));
+    assert_eq!(req.preferred_language(&["de", "de-CH"]), Some("de-CH"));
+    assert_eq!(req.preferred_language(&["ja"]), None);
+}
+
+#[test]
+fn wildcard_picks_the_first_language_not_rejected() {
+    let req = with_languages("ja, *;q=0.5, en;q=0");
+    assert_eq!(req.preferred_language(&["en", "fr"]), Some("fr"));
+    assert_eq!(req.preferred_language(&["en", "ja"]), Some("ja"));
+    assert_eq!(req.preferred_language(&["en"]), None);
+}
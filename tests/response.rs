@@ -0,0 +1,293 @@
+//! Building a `Response`: headers, bodies, status lines and content types
+use expresso::prelude::*;
+
+#[test]
+fn remove_header_ignores_case() {
+    let res = Response::new()
+        .set_header("X-Trace", "1")
+        .set_header("ETag", "\"v1\"")
+        .remove_header("x-TRACE");
+    assert!(res.header("X-Trace").is_none());
+    assert_eq!(res.header("ETag").map(String::as_str), Some("\"v1\""));
+}
+
+#[test]
+fn clear_headers_removes_everything() {
+    let res = Response::new()
+        .text("hi")
+        .set_header("ETag", "1")
+        .clear_headers();
+    assert!(res.headers.is_empty());
+    assert_eq!(res.body_text(), Some("hi"));
+}
+
+#[test]
+fn clear_body_keeps_status_and_headers() {
+    let res = Response::new()
+        .status(304)
+        .set_header("ETag", "\"v1\"")
+        .send("stale")
+        .clear_body();
+    assert!(res.body.is_none());
+    assert_eq!(res.status_code, 304);
+    assert_eq!(res.header("ETag").map(String::as_str), Some("\"v1\""));
+    let written = String::from_utf8(res.build()).unwrap();
+    assert!(written.ends_with("\r\n\r\n"));
+    assert!(!written.contains("Content-Length"), "{}", written);
+
+    let cleared = Response::new().send("gone").clear_body().build();
+    assert!(String::from_utf8(cleared)
+        .unwrap()
+        .contains("Content-Length: 0\r\n"));
+}
+
+#[test]
+fn typed_and_raw_status_codes_agree() {
+    let typed = Response::new().status(StatusCode::NOT_FOUND);
+    let raw = Response::new().status(404);
+    assert_eq!(typed.status_code, raw.status_code);
+    assert_eq!(typed.status_text, "Not Found");
+
+    assert_eq!(
+        StatusCode::from(422).to_string(),
+        "422 Unprocessable Entity"
+    );
+    assert_eq!(StatusCode::from(20).reason(), "Unknown");
+    assert!(StatusCode::CREATED.is_success());
+    assert!(StatusCode::FOUND.is_redirection());
+    assert!(StatusCode::from(429).is_client_error());
+    assert!(StatusCode::SERVICE_UNAVAILABLE.is_server_error());
+    assert!(!StatusCode::OK.is_client_error());
+}
+
+#[test]
+fn custom_reason_phrases_are_written_verbatim() {
+    let res = Response::new().status(599).reason("Upstream Melted");
+    assert_eq!(res.status_code, 599);
+    assert!(res.build().starts_with(b"HTTP/1.1 599 Upstream Melted\r\n"));
+
+    // `status` resets the phrase, and an empty one falls back to the table
+    let res = res.status(503);
+    assert_eq!(res.reason_text(), "Service Unavailable");
+    let res = Response::new().status(404).reason("");
+    assert!(res.build().starts_with(b"HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+fn content_length_counts_utf8_bytes_once() {
+    let built = Response::new()
+        .set_header("content-length", "3")
+        .send("café ☕")
+        .build();
+    let built = String::from_utf8(built).unwrap();
+    let (head, body) = built.split_once("\r\n\r\n").unwrap();
+    let lengths: Vec<_> = head
+        .split("\r\n")
+        .filter(|line| line.to_ascii_lowercase().starts_with("content-length:"))
+        .collect();
+    assert_eq!(lengths, ["Content-Length: 9"]);
+    assert_eq!(body.len(), 9);
+}
+
+#[test]
+fn binary_bodies_are_built_byte_for_byte() {
+    let payload = vec![0x89, b'P', b'N', b'G', 0x00, 0xff, 0x00];
+    let built = Response::new().send_bytes(payload.clone()).build();
+    let split = built.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let head = String::from_utf8_lossy(&built[..split]);
+    assert!(head.contains("Content-Length: 7\r\n"));
+    assert!(head.contains("Content-Type: application/octet-stream\r\n"));
+    assert_eq!(&built[split..], payload.as_slice());
+}
+
+#[test]
+fn mapped_bodies_may_become_binary() {
+    let gzip_like = vec![0x1f, 0x8b, 0x08, 0xff, 0x00, 0x80];
+    let res = Response::new()
+        .send("text")
+        .set_header("Content-Length", "4")
+        .map_body(|_| gzip_like.clone());
+    assert_eq!(res.body.as_deref(), Some(gzip_like.as_slice()));
+    assert_eq!(res.header("Content-Length").map(String::as_str), Some("6"));
+
+    let built = res.build();
+    assert!(built.ends_with(&gzip_like));
+    assert!(built.windows(19).any(|w| w == b"Content-Length: 6\r\n"));
+}
+
+#[test]
+fn map_body_sees_binary_bodies() {
+    let res = Response::new()
+        .send_static(b"\x00\x01\xfe")
+        .map_body(|body| {
+            assert_eq!(body, [0x00, 0x01, 0xfe]);
+            body.into_iter().rev().collect()
+        });
+    assert_eq!(res.body.as_deref(), Some(&[0xfe, 0x01, 0x00][..]));
+    assert_eq!(
+        res.header("Content-Type").map(String::as_str),
+        Some("application/octet-stream")
+    );
+}
+
+#[test]
+fn redirects_set_status_and_location_without_a_body() {
+    let found =
+        String::from_utf8(Response::new().send("stale").redirect("/login").build()).unwrap();
+    assert!(found.starts_with("HTTP/1.1 302 Found\r\n"));
+    assert!(found.contains("\r\nLocation: /login\r\n"));
+    assert!(found.ends_with("\r\n\r\n"));
+
+    let moved = Response::new().redirect_with_status(StatusCode::PERMANENT_REDIRECT, "/v2");
+    let moved = String::from_utf8(moved.build()).unwrap();
+    assert!(moved.starts_with("HTTP/1.1 308 Permanent Redirect\r\n"));
+    assert!(moved.contains("\r\nLocation: /v2\r\n"));
+}
+
+#[test]
+fn appended_headers_are_written_once_per_value() {
+    let res = Response::new()
+        .append_header("Set-Cookie", "a=1")
+        .append_header("Set-Cookie", "b=2")
+        .set_header("Vary", "Origin");
+    assert_eq!(res.header_all("set-cookie"), ["a=1", "b=2"]);
+
+    let built = String::from_utf8(res.set_header("vary", "Accept").build()).unwrap();
+    let lines: Vec<_> = built.split("\r\n").collect();
+    assert!(lines.contains(&"Set-Cookie: a=1"));
+    assert!(lines.contains(&"Set-Cookie: b=2"));
+    assert!(lines.contains(&"vary: Accept"));
+    assert!(!lines.contains(&"Vary: Origin"));
+}
+
+#[test]
+fn standard_codes_have_their_reason_phrases() {
+    use expresso::http::response::reason_phrase;
+
+    let expected = [
+        (100, "Continue"),
+        (204, "No Content"),
+        (301, "Moved Permanently"),
+        (401, "Unauthorized"),
+        (403, "Forbidden"),
+        (410, "Gone"),
+        (418, "I'm a Teapot"),
+        (451, "Unavailable For Legal Reasons"),
+        (502, "Bad Gateway"),
+        (505, "HTTP Version Not Supported"),
+        (511, "Network Authentication Required"),
+        (299, "Unknown"),
+        (509, "Unknown"),
+    ];
+    for (code, reason) in expected {
+        assert_eq!(reason_phrase(code), reason, "{}", code);
+    }
+    let res = String::from_utf8(Response::new().status(403).build()).unwrap();
+    assert!(res.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+}
+
+/// The `Content-Type` lines of the response as written
+fn content_types(res: &Response) -> Vec<String> {
+    String::from_utf8(res.build())
+        .unwrap()
+        .split("\r\n")
+        .filter(|line| line.starts_with("Content-Type:"))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn bodies_without_a_type_are_sent_as_utf8_text() {
+    let res = Response::new().send("plain");
+    assert_eq!(
+        content_types(&res),
+        ["Content-Type: text/plain; charset=utf-8"]
+    );
+
+    // No body, no type
+    assert!(content_types(&Response::new().status(404)).is_empty());
+}
+
+#[test]
+fn an_explicit_type_is_not_overridden() {
+    let res = Response::new()
+        .set_header("Content-Type", "application/xml")
+        .send("<a/>");
+    assert_eq!(content_types(&res), ["Content-Type: application/xml"]);
+
+    let res = Response::new().send_bytes(vec![0, 1, 2]);
+    assert_eq!(
+        content_types(&res),
+        ["Content-Type: application/octet-stream"]
+    );
+}
+
+#[test]
+fn text_types_get_one_utf8_charset() {
+    assert_eq!(
+        content_types(&Response::new().html("<p>hi</p>")),
+        ["Content-Type: text/html; charset=utf-8"]
+    );
+    assert_eq!(
+        content_types(&Response::new().json("{}")),
+        ["Content-Type: application/json; charset=utf-8"]
+    );
+    assert_eq!(
+        content_types(
+            &Response::new()
+                .content_type("text/csv; charset=ISO-8859-1")
+                .send("a,b")
+        ),
+        ["Content-Type: text/csv; charset=ISO-8859-1"]
+    );
+    assert_eq!(
+        content_types(
+            &Response::new()
+                .content_type("text/plain;Charset=UTF-8")
+                .send("hi")
+        ),
+        ["Content-Type: text/plain;Charset=UTF-8"]
+    );
+    // Binary types are left alone
+    assert_eq!(
+        content_types(
+            &Response::new()
+                .content_type("image/png")
+                .send_bytes(vec![1])
+        ),
+        ["Content-Type: image/png"]
+    );
+}
+
+#[test]
+fn appended_chunks_build_one_body() {
+    let res = Response::new()
+        .send("<ul>")
+        .append_body("<li>one</li>")
+        .append_bytes(b"<li>two</li>")
+        .append_body("</ul>");
+    assert_eq!(res.body_text(), Some("<ul><li>one</li><li>two</li></ul>"));
+    let written = String::from_utf8(res.build()).unwrap();
+    assert!(written.contains("Content-Length: 33\r\n"), "{}", written);
+
+    // Appending starts a body when there is none
+    let res = Response::new().append_body("ok");
+    assert_eq!(res.body_text(), Some("ok"));
+}
+
+#[test]
+fn appending_extends_binary_bodies_byte_for_byte() {
+    let res = Response::new()
+        .send_bytes(vec![0x89, b'P'])
+        .append_bytes(b"N\xffG")
+        .append_body("!");
+    assert_eq!(res.body.as_deref(), Some(&b"\x89PN\xffG!"[..]));
+
+    let built = res.build();
+    assert!(built.ends_with(b"\x89PN\xffG!"));
+    assert!(built.windows(19).any(|w| w == b"Content-Length: 6\r\n"));
+
+    // Static bodies are copied on the first append
+    let res = Response::new().send_static(b"\x00").append_bytes(b"\xfe");
+    assert_eq!(res.body.as_deref(), Some(&[0x00, 0xfe][..]));
+}
@@ -0,0 +1,1393 @@
+//! Full TCP round trips: bind an app on an ephemeral port, send raw requests
+//! and check the raw response bytes. Each test runs its own app, so they are
+//! independent under parallel `cargo test`.
+use expresso::prelude::*;
+use std::net::SocketAddr;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::oneshot,
+};
+
+/// App with a logging-style middleware, a GET route and a POST echo route
+async fn app() -> Expresso {
+    let app = Expresso::new();
+    app.use_middleware(|_req: Request, _res: Response, next: Next| async move {
+        next().await.set_header("X-Served-By", "expresso")
+    })
+    .await;
+    app.get(
+        "/hello",
+        (|_req: Request, res: Response, _next: Next| async move { res.status(200).send("Hello") },),
+    )
+    .await;
+    app.post(
+        "/echo",
+        (|req: Request, res: Response, _next: Next| async move {
+            let body = req.body().cloned().unwrap_or_default();
+            res.status(201).json(&body)
+        },),
+    )
+    .await;
+    app
+}
+
+/// Start the app on port 0 and wait until it is accepting connections
+async fn start(app: Expresso) -> SocketAddr {
+    let (ready, bound) = oneshot::channel();
+    tokio::spawn(async move {
+        app.listen_with_addr("127.0.0.1:0", move |addr| {
+            let _ = ready.send(addr);
+        })
+        .await
+    });
+    bound.await.expect("server failed to bind")
+}
+
+/// Send a raw request and read until the server closes the connection
+///
+/// `Connection: close` is added after the request line, if there is one, so
+/// the server closes the connection after the response instead of keeping it
+/// alive.
+async fn round_trip(addr: SocketAddr, raw: impl AsRef<[u8]>) -> String {
+    let raw = raw.as_ref();
+    let request = match raw.windows(2).position(|pair| pair == b"\r\n") {
+        Some(i) => [&raw[..i + 2], b"Connection: close\r\n", &raw[i + 2..]].concat(),
+        None => raw.to_vec(),
+    };
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(&request).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+fn split(response: &str) -> (&str, Vec<&str>, &str) {
+    let (head, body) = response.split_once("\r\n\r\n").expect("no blank line");
+    let mut lines = head.split("\r\n");
+    let status = lines.next().unwrap();
+    (status, lines.collect(), body)
+}
+
+#[tokio::test]
+async fn get_returns_status_headers_and_body() {
+    let addr = start(app().await).await;
+    let response = round_trip(addr, "GET /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(headers[0], "Content-Length: 5");
+    assert!(headers.contains(&"X-Served-By: expresso"));
+    assert!(headers.contains(&"Content-Type: text/plain; charset=utf-8"));
+    assert_eq!(body, "Hello");
+}
+
+#[tokio::test]
+async fn post_echoes_the_body() {
+    let addr = start(app().await).await;
+    let payload = r#"{"name":"alice"}"#;
+    let raw = format!(
+        "POST /echo HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n{}",
+        payload.len(),
+        payload
+    );
+    let response = round_trip(addr, &raw).await;
+
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 201 Created");
+    assert_eq!(headers[0], format!("Content-Length: {}", payload.len()));
+    assert!(headers.contains(&"Content-Type: application/json; charset=utf-8"));
+    assert_eq!(body, payload);
+}
+
+#[tokio::test]
+async fn stale_content_length_is_replaced() {
+    let app = Expresso::new();
+    app.use_middleware(|_req: Request, _res: Response, next: Next| async move {
+        let mut res = next().await;
+        res.body = res.body.map(|body| [&body[..], b" world"].concat().into());
+        res
+    })
+    .await;
+    app.get(
+        "/wrong",
+        (|_req: Request, res: Response, _next: Next| async move {
+            res.status(200)
+                .set_header("content-length", "999")
+                .send("hello")
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+    let response = round_trip(addr, "GET /wrong HTTP/1.1\r\nHost: test\r\n\r\n").await;
+
+    let (_, headers, body) = split(&response);
+    let lengths: Vec<_> = headers
+        .iter()
+        .filter(|h| h.to_ascii_lowercase().starts_with("content-length:"))
+        .collect();
+    assert_eq!(lengths, [&"Content-Length: 11"]);
+    assert_eq!(body, "hello world");
+}
+
+#[tokio::test]
+async fn rewritten_legacy_path_reaches_the_current_route() {
+    let app = app().await;
+    app.rewrite(|path| path.strip_prefix("/v1").map(str::to_string))
+        .await;
+    let addr = start(app).await;
+    let response = round_trip(addr, "GET /v1/hello?x=1 HTTP/1.1\r\nHost: test\r\n\r\n").await;
+
+    let (status, _, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(body, "Hello");
+
+    // The hook gets the raw path: escapes and dot segments are left alone
+    for raw in ["/v1%2Fhello", "/v1/./hello", "/v1/x/../hello"] {
+        let request = format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", raw);
+        let response = round_trip(addr, request).await;
+        assert_eq!(split(&response).0, "HTTP/1.1 404 Not Found", "{}", raw);
+    }
+}
+
+#[tokio::test]
+async fn unknown_route_is_404() {
+    let addr = start(app().await).await;
+    let response = round_trip(addr, "GET /missing HTTP/1.1\r\nHost: test\r\n\r\n").await;
+
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+    assert!(headers.contains(&"X-Served-By: expresso"));
+    assert_eq!(body, "Not Found");
+}
+
+#[tokio::test]
+async fn not_found_handler_replaces_the_default_404() {
+    let app = app().await;
+    app.not_found((|req: Request, res: Response, _next: Next| async move {
+        res.status(404)
+            .json_object(&[("error", "not found".into()), ("path", req.path().into())])
+    },))
+        .await;
+    app.error_handler(|_req, error| {
+        Response::new()
+            .status(500)
+            .json_object(&[("error", error.to_string().into())])
+    })
+    .await;
+    app.get(
+        "/broken",
+        (|_req: Request, res: Response, _next: Next| async move { res.fail("db down") },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(addr, "GET /missing HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+    assert!(headers
+        .iter()
+        .any(|header| header.starts_with("Content-Type: application/json")));
+    assert!(headers.contains(&"X-Served-By: expresso"));
+    assert_eq!(body, r#"{"error":"not found","path":"/missing"}"#);
+
+    // Quotes in the path stay valid JSON
+    let response = round_trip(addr, "GET /say\"hi HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(
+        split(&response).2,
+        r#"{"error":"not found","path":"/say\"hi"}"#
+    );
+
+    let response = round_trip(addr, "GET /broken HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, _, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 500 Internal Server Error");
+    assert!(body.starts_with(r#"{"error":"#), "{}", body);
+
+    // Paths with routes for other methods are still 405
+    let response = round_trip(addr, "DELETE /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(split(&response).0, "HTTP/1.1 405 Method Not Allowed");
+}
+
+#[tokio::test]
+async fn prefix_middleware_only_runs_below_its_prefix() {
+    let app = app().await;
+    app.use_middleware_for("/admin", |_req: Request, res: Response, _next: Next| async move {
+        res.status(401).send("Unauthorized")
+    })
+    .await;
+    for path in ["/admin", "/admin/users", "/public", "/administrators"] {
+        app.get(
+            path,
+            (|_req: Request, res: Response, _next: Next| async move { res.send("open") },),
+        )
+        .await;
+    }
+    let addr = start(app).await;
+
+    for (path, expected) in [
+        ("/admin", "HTTP/1.1 401 Unauthorized"),
+        ("/admin/users", "HTTP/1.1 401 Unauthorized"),
+        ("/public", "HTTP/1.1 200 OK"),
+        ("/administrators", "HTTP/1.1 200 OK"),
+    ] {
+        let request = format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", path);
+        let response = round_trip(addr, request).await;
+        let (status, headers, _) = split(&response);
+        assert_eq!(status, expected, "{}", path);
+        // Global middleware still runs everywhere
+        assert!(headers.contains(&"X-Served-By: expresso"), "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn map_body_middleware_keeps_content_length_in_sync() {
+    let app = Expresso::new();
+    app.use_middleware(|_req: Request, _res: Response, next: Next| async move {
+        next()
+            .await
+            .map_body(|body| String::from_utf8_lossy(&body).to_uppercase().into_bytes())
+    })
+    .await;
+    app.get(
+        "/page",
+        (|_req: Request, res: Response, _next: Next| async move {
+            // `ß` uppercases to `SS`, so the body grows by one byte
+            let page = "<p>straße</p>";
+            res.html(page)
+                .set_header("Content-Length", &page.len().to_string())
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(addr, "GET /page HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(body, "<P>STRASSE</P>");
+    let lengths: Vec<_> = headers
+        .iter()
+        .filter(|header| header.starts_with("Content-Length:"))
+        .collect();
+    assert_eq!(lengths, [&format!("Content-Length: {}", body.len())]);
+}
+
+#[tokio::test]
+async fn trace_is_405() {
+    let addr = start(app().await).await;
+    let response = round_trip(addr, "TRACE /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+
+    let (status, _, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+    assert_eq!(body, "Method Not Allowed");
+}
+
+#[tokio::test]
+async fn trace_and_connect_are_answered_deliberately() {
+    use expresso::server::config::ServerConfig;
+
+    let addr = start(app().await).await;
+    // TRACE is refused even on a routed path unless enabled
+    let response = round_trip(addr, "TRACE /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(split(&response).0, "HTTP/1.1 405 Method Not Allowed");
+    // Tunnelling isn't built in, so an unrouted CONNECT is unimplemented
+    let response = round_trip(
+        addr,
+        "CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n",
+    )
+    .await;
+    let (status, _, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 501 Not Implemented");
+    assert_eq!(body, "Not Implemented");
+
+    // With TRACE enabled it is routed like any other method
+    let app = Expresso::new().with_config(ServerConfig::new().allow_trace(true));
+    app.route(
+        Method::TRACE,
+        "/echo",
+        (|req: Request, res: Response, _next: Next| async move {
+            let method = req.method().to_string();
+            res.send(&method)
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+    let response = round_trip(addr, "TRACE /echo HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, _, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(body, "TRACE");
+    let response = round_trip(addr, "TRACE /other HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(split(&response).0, "HTTP/1.1 404 Not Found");
+}
+
+#[tokio::test]
+async fn files_larger_than_the_chunk_size_are_sent_whole() {
+    use expresso::server::config::ServerConfig;
+
+    let contents: String = (0..100_003)
+        .map(|i| char::from(b'a' + (i % 26) as u8))
+        .collect();
+    let path = std::env::temp_dir().join(format!("expresso-chunked-{}.txt", std::process::id()));
+    std::fs::write(&path, &contents).unwrap();
+
+    let app = Expresso::new().with_config(ServerConfig::new().file_chunk_size(4096));
+    let served = path.clone();
+    app.get(
+        "/big",
+        (move |_req: Request, res: Response, _next: Next| {
+            let served = served.clone();
+            async move { res.send_file(&served).await }
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(addr, "GET /big HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    std::fs::remove_file(&path).unwrap();
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(headers.contains(&"Content-Length: 100003"));
+    assert!(body == contents, "body differs: {} bytes", body.len());
+}
+
+#[tokio::test]
+async fn malformed_request_line_is_400() {
+    let addr = start(app().await).await;
+    let response = round_trip(addr, "NONSENSE\r\n\r\n").await;
+
+    let (status, _, _) = split(&response);
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+}
+
+#[tokio::test]
+async fn hijacked_connection_is_handed_to_the_handler() {
+    let app = app().await;
+    app.get(
+        "/upgrade",
+        (|_req: Request, res: Response, _next: Next| async move {
+            let (res, hijack) = res.hijack();
+            tokio::spawn(async move {
+                let mut conn = hijack.await.unwrap();
+                let mut early = conn.buffered.clone();
+                while early.len() < 4 {
+                    let mut more = [0; 4];
+                    let n = conn.stream.read(&mut more).await.unwrap();
+                    early.extend_from_slice(&more[..n]);
+                }
+                let mut reply = b"HTTP/1.1 101 Switching Protocols\r\n\r\n".to_vec();
+                reply.extend_from_slice(&early);
+                conn.stream.write_all(&reply).await.unwrap();
+            });
+            res
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    // Bytes sent right after the request reach the handler, not the server
+    let response = round_trip(addr, "GET /upgrade HTTP/1.1\r\nHost: test\r\n\r\nping").await;
+    assert_eq!(response, "HTTP/1.1 101 Switching Protocols\r\n\r\nping");
+}
+
+#[tokio::test]
+async fn route_default_content_type_yields_to_explicit_header() {
+    use expresso::middleware::default_content_type;
+
+    let app = app().await;
+    app.get(
+        "/plain",
+        (
+            default_content_type("application/json"),
+            |_req: Request, res: Response, _next: Next| async move { res.send("[]") },
+        ),
+    )
+    .await;
+    app.group("/docs", |g| {
+        g.default_content_type("application/json");
+        g.get(
+            "/page",
+            (|_req: Request, res: Response, _next: Next| async move { res.html("<p>hi</p>") },),
+        );
+    })
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(addr, "GET /plain HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (_, headers, _) = split(&response);
+    assert!(headers.contains(&"Content-Type: application/json; charset=utf-8"));
+
+    let response = round_trip(addr, "GET /docs/page HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (_, headers, _) = split(&response);
+    assert!(headers.contains(&"Content-Type: text/html; charset=utf-8"));
+}
+
+#[tokio::test]
+async fn connection_context_persists_across_keep_alive_requests() {
+    use expresso::server::config::ServerConfig;
+
+    let app = Expresso::new().with_config(ServerConfig::new().keep_alive(true));
+    app.use_middleware(|req: Request, _res: Response, next: Next| async move {
+        let seen = req.connection().get::<Vec<String>>().unwrap_or_default();
+        let mut paths = seen.clone();
+        paths.push(req.path().to_string());
+        req.connection().insert(paths);
+        next().await.set_header("X-Seen", &seen.join(","))
+    })
+    .await;
+    for path in ["/one", "/two"] {
+        app.get(
+            path,
+            (|req: Request, res: Response, _next: Next| async move {
+                res.send(&req.connection().request_count().to_string())
+            },),
+        )
+        .await;
+    }
+    let addr = start(app).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut responses = Vec::new();
+    for path in ["/one", "/two"] {
+        let raw = format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", path);
+        stream.write_all(raw.as_bytes()).await.unwrap();
+        responses.push(read_response(&mut stream).await);
+    }
+
+    let (_, headers, body) = split(&responses[0]);
+    assert!(headers.contains(&"X-Seen: "));
+    assert_eq!(body, "1");
+    let (_, headers, body) = split(&responses[1]);
+    assert!(headers.contains(&"X-Seen: /one"));
+    assert_eq!(body, "2");
+}
+
+/// Read one response framed by Content-Length, leaving the connection open
+async fn read_response(stream: &mut TcpStream) -> String {
+    let mut raw = Vec::new();
+    let mut byte = [0; 1];
+    while !raw.ends_with(b"\r\n\r\n") {
+        stream.read_exact(&mut byte).await.unwrap();
+        raw.push(byte[0]);
+    }
+    let head = String::from_utf8(raw.clone()).unwrap();
+    let len: usize = head
+        .lines()
+        .find_map(|line| line.strip_prefix("Content-Length: "))
+        .map_or(0, |len| len.parse().unwrap());
+    let mut body = vec![0; len];
+    stream.read_exact(&mut body).await.unwrap();
+    raw.extend_from_slice(&body);
+    String::from_utf8(raw).unwrap()
+}
+
+#[tokio::test]
+async fn on_response_reports_every_written_response() {
+    use std::sync::{Arc, Mutex};
+
+    let app = app().await;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    app.on_response(move |req, res, _elapsed, bytes| {
+        let entry = (req.path().to_string(), res.status_code, bytes);
+        log.lock().unwrap().push(entry);
+    })
+    .await;
+    let addr = start(app).await;
+
+    let ok = round_trip(addr, "GET /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let missing = round_trip(addr, "GET /nope HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let malformed = round_trip(addr, "GARBAGE\r\n\r\n").await;
+
+    let seen = seen.lock().unwrap().clone();
+    assert_eq!(
+        seen,
+        vec![
+            ("/hello".to_string(), 200, ok.len() as u64),
+            ("/nope".to_string(), 404, missing.len() as u64),
+            (String::new(), 400, malformed.len() as u64),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn any_route_is_the_fallback_after_the_exact_method() {
+    let app = app().await;
+    app.get(
+        "/x",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("get") },),
+    )
+    .await;
+    app.any(
+        "/x",
+        (|req: Request, res: Response, _next: Next| async move {
+            res.send(&format!("any {}", req.method()))
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let specific = round_trip(addr, "GET /x HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(split(&specific).2, "get");
+    let fallback = round_trip(addr, "DELETE /x HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(split(&fallback).2, "any DELETE");
+    let miss = round_trip(addr, "GET /y HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(split(&miss).0, "HTTP/1.1 404 Not Found");
+}
+
+#[tokio::test]
+async fn allow_lists_the_methods_registered_for_the_path() {
+    let app = app().await;
+    app.post(
+        "/hello",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("posted") },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(addr, "PUT /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, _) = split(&response);
+    assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+    assert!(headers.contains(&"Allow: GET, POST, HEAD, OPTIONS"));
+
+    let options = round_trip(addr, "OPTIONS /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, body) = split(&options);
+    assert_eq!(status, "HTTP/1.1 204 No Content");
+    assert!(headers.contains(&"Allow: GET, POST, HEAD, OPTIONS"));
+    assert_eq!(body, "");
+
+    // The server as a whole: methods registered on any path
+    let any = round_trip(addr, "OPTIONS * HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert!(split(&any).1.contains(&"Allow: GET, POST, HEAD, OPTIONS"));
+}
+
+#[tokio::test]
+async fn automatic_options_answer_has_no_framing_headers() {
+    let addr = start(app().await).await;
+    let response = round_trip(addr, "OPTIONS /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 204 No Content");
+    assert!(!headers
+        .iter()
+        .any(|header| header.starts_with("Content-Length:")
+            || header.starts_with("Transfer-Encoding:")));
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn wrong_method_on_a_parameter_route_is_405_and_unknown_paths_404() {
+    let app = app().await;
+    app.get(
+        "/users/:id",
+        (|req: Request, res: Response, _next: Next| async move {
+            let id = req.param("id").cloned().unwrap_or_default();
+            res.send(&id)
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(addr, "POST /users/42 HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+    assert!(headers.contains(&"Allow: GET, HEAD, OPTIONS"));
+    assert_eq!(body, "Method Not Allowed");
+
+    let response = round_trip(addr, "POST /groups/42 HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, _) = split(&response);
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+    assert!(!headers.iter().any(|header| header.starts_with("Allow:")));
+}
+
+#[tokio::test]
+async fn stalled_body_gets_408_and_idle_connections_close_silently() {
+    use expresso::server::config::ServerConfig;
+    use std::time::Duration;
+
+    let config = ServerConfig::new()
+        .read_timeout(Duration::from_millis(100))
+        .idle_timeout(Duration::from_millis(200));
+    let app = app().await.with_config(config);
+    let addr = start(app).await;
+
+    // Content-Length promises 100 bytes, only 4 arrive
+    let stalled = round_trip(
+        addr,
+        "POST /echo HTTP/1.1\r\nHost: test\r\nContent-Length: 100\r\n\r\nabcd",
+    )
+    .await;
+    assert_eq!(split(&stalled).0, "HTTP/1.1 408 Request Timeout");
+
+    let idle = round_trip(addr, "").await;
+    assert_eq!(idle, "");
+}
+
+#[tokio::test]
+async fn bytes_after_a_body_start_the_next_keep_alive_request() {
+    use expresso::server::config::ServerConfig;
+
+    let app = app()
+        .await
+        .with_config(ServerConfig::new().keep_alive(true));
+    let addr = start(app).await;
+
+    // Request A's body and the first half of request B arrive in one write
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let first = "POST /echo HTTP/1.1\r\nHost: test\r\nContent-Length: 5\r\n\r\nhelloGET /hel";
+    stream.write_all(first.as_bytes()).await.unwrap();
+    let echoed = read_response(&mut stream).await;
+    stream
+        .write_all(b"lo HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let hello = read_response(&mut stream).await;
+
+    assert_eq!(split(&echoed).0, "HTTP/1.1 201 Created");
+    assert_eq!(split(&echoed).2, "hello");
+    assert_eq!(split(&hello).0, "HTTP/1.1 200 OK");
+    assert_eq!(split(&hello).2, "Hello");
+}
+
+#[tokio::test]
+async fn gzipped_bodies_reach_handlers_decoded() {
+    use expresso::server::config::ServerConfig;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn gzip_post(body: &[u8], encoding: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let mut raw = format!(
+            "POST /echo HTTP/1.1\r\nHost: test\r\nContent-Encoding: {}\r\nContent-Length: {}\r\n\r\n",
+            encoding,
+            gzipped.len()
+        )
+        .into_bytes();
+        raw.extend_from_slice(&gzipped);
+        raw
+    }
+
+    let app = app()
+        .await
+        .with_config(ServerConfig::new().max_decompressed_size(1024));
+    let addr = start(app).await;
+
+    let payload = r#"{"name":"alice"}"#;
+    let response = round_trip(addr, gzip_post(payload.as_bytes(), "gzip")).await;
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 201 Created");
+    assert_eq!(headers[0], format!("Content-Length: {}", payload.len()));
+    assert_eq!(body, payload);
+
+    // 4 KiB of zeros compresses to a few bytes but inflates past the limit
+    let bomb = round_trip(addr, gzip_post(&[0; 4096], "gzip")).await;
+    assert_eq!(split(&bomb).0, "HTTP/1.1 413 Payload Too Large");
+
+    let unsupported = round_trip(addr, gzip_post(payload.as_bytes(), "br")).await;
+    let (status, headers, _) = split(&unsupported);
+    assert_eq!(status, "HTTP/1.1 415 Unsupported Media Type");
+    assert!(headers.contains(&"Accept-Encoding: gzip, deflate"));
+}
+
+#[tokio::test]
+async fn maintenance_mode_flips_while_running() {
+    use expresso::middlewares::maintenance::maintenance;
+
+    let app = Expresso::new();
+    app.use_middleware(maintenance(app.maintenance_flag()))
+        .await;
+    for path in ["/hello", "/health"] {
+        app.get(
+            path,
+            (|_req: Request, res: Response, _next: Next| async move { res.send("up") },),
+        )
+        .await;
+    }
+    let flag = app.maintenance_flag();
+    let addr = start(app).await;
+    let hello = "GET /hello HTTP/1.1\r\nHost: test\r\n\r\n";
+
+    assert_eq!(split(&round_trip(addr, hello).await).0, "HTTP/1.1 200 OK");
+
+    flag.set(true);
+    let down = round_trip(addr, hello).await;
+    let (status, headers, _) = split(&down);
+    assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+    assert!(headers.contains(&"Retry-After: 120"));
+    let health = round_trip(addr, "GET /health HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(split(&health).2, "up");
+
+    flag.set(false);
+    assert_eq!(split(&round_trip(addr, hello).await).0, "HTTP/1.1 200 OK");
+}
+
+#[tokio::test]
+async fn optional_trailing_params_match_present_and_absent() {
+    let app = Expresso::new();
+    app.get(
+        "/posts/:year/:month?",
+        (|req: Request, res: Response, _next: Next| async move {
+            let param = |name| req.params().get(name).cloned();
+            res.send(&format!("{:?} {:?}", param("year"), param("month")))
+        },),
+    )
+    .await;
+    app.get(
+        "/posts/archive",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("archive") },),
+    )
+    .await;
+    let addr = start(app).await;
+    let get = |path: &str| format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", path);
+
+    let absent = round_trip(addr, get("/posts/2024")).await;
+    assert_eq!(split(&absent).2, r#"Some("2024") None"#);
+    let present = round_trip(addr, get("/posts/2024/03")).await;
+    assert_eq!(split(&present).2, r#"Some("2024") Some("03")"#);
+    // A static route wins over the pattern it also matches
+    let archive = round_trip(addr, get("/posts/archive")).await;
+    assert_eq!(split(&archive).2, "archive");
+    let too_long = round_trip(addr, get("/posts/2024/03/01")).await;
+    assert_eq!(split(&too_long).0, "HTTP/1.1 404 Not Found");
+}
+
+#[tokio::test]
+async fn invalid_headers_fall_back_to_a_plain_500() {
+    let app = Expresso::new();
+    app.get(
+        "/bad",
+        (|_req: Request, res: Response, _next: Next| async move {
+            // A space in a header name can't be written as valid HTTP
+            res.set_header("Bad Name", "x").send("never sent")
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(addr, "GET /bad HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 500 Internal Server Error");
+    assert!(!headers.iter().any(|h| h.starts_with("Bad Name")));
+    assert_eq!(body, "500 Internal Server Error");
+}
+
+#[tokio::test]
+async fn group_error_boundary_overrides_the_global_hook() {
+    let app = Expresso::new();
+    app.on_error(|_req, err| Response::new().status(500).html(&format!("<p>{}</p>", err)))
+        .await;
+    let fail = |_req: Request, res: Response, _next: Next| async move {
+        res.fail(std::io::Error::other("db down"))
+    };
+    app.get("/page", (fail,)).await;
+    app.group("/api", |api| {
+        api.on_error(|_req, err| {
+            Response::new()
+                .status(503)
+                .json_object(&[("error", err.to_string().into())])
+        });
+        api.get("/items", (fail,));
+    })
+    .await;
+    let addr = start(app).await;
+
+    let api = round_trip(addr, "GET /api/items HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, _, body) = split(&api);
+    assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+    assert_eq!(body, r#"{"error":"db down"}"#);
+
+    let page = round_trip(addr, "GET /page HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(split(&page).2, "<p>db down</p>");
+}
+
+/// Concatenate the chunks of a chunked body, `None` if it is cut short
+#[cfg(feature = "serde")]
+fn dechunk(mut body: &str) -> Option<String> {
+    let mut out = String::new();
+    loop {
+        let (size, rest) = body.split_once("\r\n")?;
+        let size = usize::from_str_radix(size, 16).ok()?;
+        if size == 0 {
+            return Some(out);
+        }
+        out.push_str(rest.get(..size)?);
+        body = rest.get(size + 2..)?;
+    }
+}
+
+#[cfg(feature = "serde")]
+#[tokio::test]
+async fn json_stream_sends_a_chunked_array() {
+    use futures::{stream, StreamExt};
+
+    let app = Expresso::new();
+    app.get(
+        "/items",
+        (|_req: Request, res: Response, _next: Next| async move {
+            res.json_stream(stream::iter(0..1000).map(|id| serde_json::json!({ "id": id })))
+        },),
+    )
+    .await;
+    app.get(
+        "/empty",
+        (|_req: Request, res: Response, _next: Next| async move {
+            res.json_stream(stream::iter(Vec::<u32>::new()))
+        },),
+    )
+    .await;
+    app.get(
+        "/broken",
+        (|_req: Request, res: Response, _next: Next| async move {
+            res.try_json_stream(stream::iter([Ok(1), Err("cursor lost")]))
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+    let get = |path: &str| format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", path);
+
+    let response = round_trip(addr, get("/items")).await;
+    let (_, headers, body) = split(&response);
+    assert!(headers.contains(&"Transfer-Encoding: chunked"));
+    let items: Vec<serde_json::Value> = serde_json::from_str(&dechunk(body).unwrap()).unwrap();
+    assert_eq!(items.len(), 1000);
+    assert_eq!(items[999]["id"], 999);
+
+    let empty = round_trip(addr, get("/empty")).await;
+    assert_eq!(dechunk(split(&empty).2).as_deref(), Some("[]"));
+
+    // The connection closes without the final chunk
+    let broken = round_trip(addr, get("/broken")).await;
+    assert_eq!(dechunk(split(&broken).2), None);
+}
+
+#[tokio::test]
+async fn chunked_bodies_end_with_their_trailers() {
+    let app = Expresso::new();
+    app.get(
+        "/signed",
+        (|_req: Request, res: Response, _next: Next| async move {
+            let (res, body) = res.trailer("X-Checksum").trailer("X-Count").chunked();
+            tokio::spawn(async move {
+                body.send("Hello, ").await?;
+                body.send("").await?;
+                body.send("world").await?;
+                body.finish_with_trailers(&[("X-Checksum", "abc123"), ("X-Count", "2")])
+                    .await
+            });
+            res
+        },),
+    )
+    .await;
+    app.get(
+        "/plain",
+        (|_req: Request, res: Response, _next: Next| async move {
+            let (res, body) = res.chunked();
+            tokio::spawn(async move { body.send("done").await });
+            res
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(addr, "GET /signed HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(headers.contains(&"Transfer-Encoding: chunked"));
+    assert!(headers.contains(&"Trailer: X-Checksum, X-Count"));
+    assert!(!headers
+        .iter()
+        .any(|line| line.starts_with("Content-Length")));
+    // Empty sends are skipped, and the trailer fields follow the last chunk
+    assert_eq!(
+        body,
+        "7\r\nHello, \r\n5\r\nworld\r\n0\r\nX-Checksum: abc123\r\nX-Count: 2\r\n\r\n"
+    );
+
+    // Dropping the sender ends the body with an empty trailer section
+    let response = round_trip(addr, "GET /plain HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert_eq!(split(&response).2, "4\r\ndone\r\n0\r\n\r\n");
+}
+
+#[tokio::test]
+async fn concurrency_limit_rejects_or_queues_overflow() {
+    use expresso::middlewares::concurrency::ConcurrencyConfig;
+    use std::time::Duration;
+
+    async fn slow_app(config: ConcurrencyConfig) -> SocketAddr {
+        let app = Expresso::new();
+        app.use_middleware(config.build()).await;
+        app.get(
+            "/slow",
+            (|_req: Request, res: Response, _next: Next| async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                res.send("done")
+            },),
+        )
+        .await;
+        start(app).await
+    }
+    async fn statuses(addr: SocketAddr, count: usize) -> Vec<String> {
+        let requests = (0..count)
+            .map(|_| tokio::spawn(round_trip(addr, "GET /slow HTTP/1.1\r\nHost: test\r\n\r\n")));
+        let mut statuses = Vec::new();
+        for request in requests.collect::<Vec<_>>() {
+            statuses.push(split(&request.await.unwrap()).0.to_string());
+        }
+        statuses.sort();
+        statuses
+    }
+
+    let reject = slow_app(ConcurrencyConfig::new(2)).await;
+    assert_eq!(
+        statuses(reject, 4).await,
+        [
+            "HTTP/1.1 200 OK",
+            "HTTP/1.1 200 OK",
+            "HTTP/1.1 503 Service Unavailable",
+            "HTTP/1.1 503 Service Unavailable"
+        ]
+    );
+
+    // Queued requests get a permit as earlier ones finish
+    let queue = slow_app(ConcurrencyConfig::new(2).queue(Duration::from_secs(5))).await;
+    assert!(statuses(queue, 4)
+        .await
+        .iter()
+        .all(|status| status == "HTTP/1.1 200 OK"));
+}
+
+#[tokio::test]
+async fn next_combinators_post_process_the_chain() {
+    let app = Expresso::new();
+    app.use_middleware(|_req: Request, _res: Response, next: Next| {
+        next.map(|res| res.set_header("X-Mapped", "1"))
+    })
+    .await;
+    app.use_middleware(|_req: Request, _res: Response, next: Next| {
+        next.then(|res| async move {
+            tokio::task::yield_now().await;
+            let body = res.body_text().unwrap_or_default().to_uppercase();
+            res.send(&body)
+        })
+    })
+    .await;
+    app.get(
+        "/",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("hello") },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(addr, "GET / HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(headers.contains(&"X-Mapped: 1"));
+    assert_eq!(body, "HELLO");
+}
+
+#[tokio::test]
+async fn framework_errors_follow_the_accept_header() {
+    let app = Expresso::new();
+    app.get(
+        "/only-get",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("ok") },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let response = round_trip(
+        addr,
+        "GET /missing HTTP/1.1\r\nHost: test\r\nAccept: application/json\r\n\r\n",
+    )
+    .await;
+    let (status, headers, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+    assert!(headers
+        .iter()
+        .any(|header| header.starts_with("Content-Type: application/json")));
+    assert_eq!(body, r#"{"error":"Not Found","status":404}"#);
+
+    // Browsers and clients without a preference keep plain text
+    let response = round_trip(
+        addr,
+        "POST /only-get HTTP/1.1\r\nHost: test\r\nAccept: text/html,*/*;q=0.8\r\n\r\n",
+    )
+    .await;
+    let (status, _, body) = split(&response);
+    assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+    assert_eq!(body, "Method Not Allowed");
+}
+
+#[tokio::test]
+async fn error_formats_can_be_replaced() {
+    let app = Expresso::new();
+    app.error_format("application/json", |code, _message| {
+        Response::new()
+            .status(code)
+            .json(&format!(r#"{{"code":{}}}"#, code))
+    })
+    .await;
+    app.error_format("text/html", |code, message| {
+        Response::new()
+            .status(code)
+            .html(&format!("<h1>{}</h1>", message))
+    })
+    .await;
+    let addr = start(app).await;
+
+    let json = round_trip(
+        addr,
+        "GET /missing HTTP/1.1\r\nHost: test\r\nAccept: application/json\r\n\r\n",
+    )
+    .await;
+    assert_eq!(split(&json).2, r#"{"code":404}"#);
+    let html = round_trip(
+        addr,
+        "GET /missing HTTP/1.1\r\nHost: test\r\nAccept: text/html\r\n\r\n",
+    )
+    .await;
+    assert_eq!(split(&html).2, "<h1>Not Found</h1>");
+}
+
+#[tokio::test]
+async fn embedded_assets_get_cache_defaults_by_name() {
+    use expresso::middlewares::embedded::serve_embedded;
+
+    let app = Expresso::new();
+    app.use_middleware(serve_embedded(
+        "/static",
+        &[
+            ("app.3f9a2c1b.js", b"console.log(1)", ""),
+            ("index.html", b"<h1>hi</h1>", ""),
+        ],
+    ))
+    .await;
+    let addr = start(app).await;
+
+    let hashed = round_trip(
+        addr,
+        "GET /static/app.3f9a2c1b.js HTTP/1.1\r\nHost: test\r\n\r\n",
+    )
+    .await;
+    assert!(split(&hashed)
+        .1
+        .contains(&"Cache-Control: public, max-age=31536000, immutable"));
+    let index = round_trip(addr, "GET /static/ HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    assert!(split(&index).1.contains(&"Cache-Control: no-cache"));
+
+    let head = round_trip(
+        addr,
+        "HEAD /static/index.html HTTP/1.1\r\nHost: test\r\n\r\n",
+    )
+    .await;
+    let (status, headers, body) = split(&head);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(headers.contains(&"Content-Length: 11"));
+    assert_eq!(body, "");
+
+    let etag = headers
+        .iter()
+        .find_map(|header| header.strip_prefix("ETag: "))
+        .unwrap();
+    let request = format!(
+        "GET /static/index.html HTTP/1.1\r\nHost: test\r\nIf-None-Match: {}\r\n\r\n",
+        etag
+    );
+    let not_modified = round_trip(addr, request).await;
+    let (status, headers, body) = split(&not_modified);
+    assert_eq!(status, "HTTP/1.1 304 Not Modified");
+    assert!(!headers
+        .iter()
+        .any(|header| header.starts_with("Content-Length:")));
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn path_params_are_captured_by_name() {
+    let app = Expresso::new();
+    app.get(
+        "/users/:id",
+        (|req: Request, res: Response, _next: Next| async move {
+            res.send(&format!("user {}", req.param("id").unwrap()))
+        },),
+    )
+    .await;
+    app.get(
+        "/users/me",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("me") },),
+    )
+    .await;
+    app.get(
+        "/a/:x/b/:y",
+        (|req: Request, res: Response, _next: Next| async move {
+            let x = req.param("x").unwrap();
+            let y = req.param("y").unwrap();
+            res.send(&format!("x={} y={}", x, y))
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+    let get = |path: &str| format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", path);
+
+    let user = round_trip(addr, get("/users/42")).await;
+    assert_eq!(split(&user).2, "user 42");
+    let decoded = round_trip(addr, get("/users/j%C3%BCrgen")).await;
+    assert_eq!(split(&decoded).2, "user jürgen");
+    // The static route wins over the pattern
+    let me = round_trip(addr, get("/users/me")).await;
+    assert_eq!(split(&me).2, "me");
+    let nested = round_trip(addr, get("/a/1/b/2")).await;
+    assert_eq!(split(&nested).2, "x=1 y=2");
+
+    for path in ["/a/1/c/2", "/users", "/users/42/posts"] {
+        let missing = round_trip(addr, get(path)).await;
+        assert_eq!(split(&missing).0, "HTTP/1.1 404 Not Found", "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn wildcards_capture_the_rest_of_the_path() {
+    let app = Expresso::new();
+    app.get(
+        "/static/*path",
+        (|req: Request, res: Response, _next: Next| async move {
+            res.send(&format!("tail [{}]", req.param("path").unwrap()))
+        },),
+    )
+    .await;
+    app.get(
+        "/static/js/:file",
+        (|req: Request, res: Response, _next: Next| async move {
+            res.send(&format!("file {}", req.param("file").unwrap()))
+        },),
+    )
+    .await;
+    app.get(
+        "/static/favicon.ico",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("icon") },),
+    )
+    .await;
+    let addr = start(app).await;
+    let get = |path: &str| format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", path);
+
+    let nested = round_trip(addr, get("/static/css/app.css")).await;
+    assert_eq!(split(&nested).2, "tail [css/app.css]");
+    let encoded = round_trip(addr, get("/static/a%2Fb/c%20d")).await;
+    assert_eq!(split(&encoded).2, "tail [a%2Fb/c%20d]");
+    let empty = round_trip(addr, get("/static/")).await;
+    assert_eq!(split(&empty).2, "tail []");
+    let single = round_trip(addr, get("/static/app.js")).await;
+    assert_eq!(split(&single).2, "tail [app.js]");
+    // Params and static routes are more specific than the wildcard
+    let param = round_trip(addr, get("/static/js/app.js")).await;
+    assert_eq!(split(&param).2, "file app.js");
+    let deeper = round_trip(addr, get("/static/js/vendor/lib.js")).await;
+    assert_eq!(split(&deeper).2, "tail [js/vendor/lib.js]");
+    let icon = round_trip(addr, get("/static/favicon.ico")).await;
+    assert_eq!(split(&icon).2, "icon");
+    let outside = round_trip(addr, get("/static")).await;
+    assert_eq!(split(&outside).0, "HTTP/1.1 404 Not Found");
+}
+
+#[tokio::test]
+async fn large_bodies_are_read_in_full() {
+    use expresso::server::config::ServerConfig;
+
+    // A small read buffer splits both the head and the body across reads
+    let config = ServerConfig::default()
+        .read_buffer_size(512)
+        .max_request_size(16 * 1024);
+    let app = Expresso::new().with_config(config);
+    app.post(
+        "/upload",
+        (|req: Request, res: Response, _next: Next| async move {
+            let body = req.body().cloned().unwrap_or_default();
+            let intact = body
+                .bytes()
+                .enumerate()
+                .all(|(i, b)| b == b'a' + (i % 26) as u8);
+            res.send(&format!("{} {}", body.len(), intact))
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let body: String = (0..10 * 1024)
+        .map(|i| (b'a' + (i % 26) as u8) as char)
+        .collect();
+    let padding = "x".repeat(600);
+    let request = format!(
+        "POST /upload HTTP/1.1\r\nHost: test\r\nX-Padding: {}\r\nContent-Length: {}\r\n\r\n{}",
+        padding,
+        body.len(),
+        body
+    );
+    let response = round_trip(addr, &request).await;
+    assert_eq!(split(&response).2, "10240 true");
+
+    let oversized = format!(
+        "POST /upload HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n",
+        64 * 1024
+    );
+    let response = round_trip(addr, &oversized).await;
+    assert_eq!(split(&response).0, "HTTP/1.1 413 Payload Too Large");
+}
+
+#[tokio::test]
+async fn every_method_helper_registers_its_route() {
+    let app = Expresso::new();
+    let handler = || {
+        (|req: Request, res: Response, _next: Next| async move {
+            let method = req.method().to_string();
+            res.set_header("X-Method", &method)
+        },)
+    };
+    app.get("/r", handler()).await;
+    app.post("/r", handler()).await;
+    app.put("/r", handler()).await;
+    app.delete("/r", handler()).await;
+    app.patch("/r", handler()).await;
+    app.head("/r", handler()).await;
+    app.options("/r", handler()).await;
+    let addr = start(app).await;
+
+    for method in ["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS"] {
+        let request = format!("{} /r HTTP/1.1\r\nHost: test\r\n\r\n", method);
+        let response = round_trip(addr, &request).await;
+        let (status, headers, _) = split(&response);
+        assert_eq!(status, "HTTP/1.1 200 OK", "{}", method);
+        assert!(headers.contains(&format!("X-Method: {}", method).as_str()));
+    }
+}
+
+#[tokio::test]
+async fn head_runs_the_get_route_without_its_body() {
+    let app = app().await;
+    app.get(
+        "/both",
+        (|_req: Request, res: Response, _next: Next| async move {
+            res.set_header("X-Route", "get").send("from get")
+        },),
+    )
+    .await;
+    app.head(
+        "/both",
+        (|_req: Request, res: Response, _next: Next| async move {
+            res.set_header("X-Route", "head")
+        },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    let get = round_trip(addr, "GET /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let head = round_trip(addr, "HEAD /hello HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (get_status, get_headers, get_body) = split(&get);
+    let (head_status, head_headers, head_body) = split(&head);
+    assert_eq!(get_body, "Hello");
+    assert_eq!(head_status, get_status);
+    assert_eq!(head_headers, get_headers);
+    assert!(head_headers.contains(&"Content-Length: 5"));
+    assert_eq!(head_body, "");
+
+    // An explicit HEAD route still wins over the GET route
+    let head = round_trip(addr, "HEAD /both HTTP/1.1\r\nHost: test\r\n\r\n").await;
+    let (status, headers, body) = split(&head);
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(headers.contains(&"X-Route: head"));
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn all_routes_every_method_behind_specific_ones() {
+    let app = Expresso::new();
+    app.all(
+        "/proxy",
+        (|req: Request, res: Response, _next: Next| async move {
+            res.send(&format!("all {}", req.method()))
+        },),
+    )
+    .await;
+    app.post(
+        "/proxy",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("post") },),
+    )
+    .await;
+    let addr = start(app).await;
+
+    for method in ["GET", "DELETE"] {
+        let request = format!("{} /proxy HTTP/1.1\r\nHost: test\r\n\r\n", method);
+        let response = round_trip(addr, &request).await;
+        assert_eq!(split(&response).2, format!("all {}", method));
+    }
+    let post = round_trip(
+        addr,
+        "POST /proxy HTTP/1.1\r\nHost: test\r\nContent-Length: 0\r\n\r\n",
+    )
+    .await;
+    assert_eq!(split(&post).2, "post");
+}
+
+#[tokio::test]
+async fn connections_stay_open_until_the_client_closes_them() {
+    let addr = start(app().await).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    let mut heads = Vec::new();
+    for extra in ["", "Connection: close\r\n"] {
+        let raw = format!("GET /hello HTTP/1.1\r\nHost: test\r\n{}\r\n", extra);
+        stream.write_all(raw.as_bytes()).await.unwrap();
+        let response = read_response(&mut stream).await;
+        assert_eq!(split(&response).2, "Hello");
+        heads.push(split(&response).1.join("\n"));
+    }
+    assert!(!heads[0].contains("Connection:"));
+    assert!(heads[1].contains("Connection: close"));
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).await.unwrap();
+    assert!(rest.is_empty(), "connection stayed open after close");
+
+    let mut legacy = TcpStream::connect(addr).await.unwrap();
+    legacy
+        .write_all(b"GET /hello HTTP/1.0\r\nConnection: keep-alive\r\n\r\n")
+        .await
+        .unwrap();
+    let response = read_response(&mut legacy).await;
+    assert!(split(&response).1.contains(&"Connection: keep-alive"));
+}
+
+#[tokio::test]
+async fn half_closed_clients_get_their_response_unless_cancellation_is_on() {
+    use expresso::server::config::ServerConfig;
+    use std::time::Duration;
+
+    let slow = |config: ServerConfig| async move {
+        let app = Expresso::new().with_config(config);
+        app.get(
+            "/slow",
+            (|_req: Request, res: Response, _next: Next| async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                res.send("finished")
+            },),
+        )
+        .await;
+        let addr = start(app).await;
+
+        // Send the request, then shut the write side while the handler runs
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        stream.shutdown().await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    };
+
+    let response = slow(ServerConfig::new()).await;
+    assert_eq!(split(&response).2, "finished");
+
+    // Opting in treats the half-close as the client leaving
+    let response = slow(ServerConfig::new().cancel_on_disconnect(true)).await;
+    assert_eq!(response, "");
+}
@@ -0,0 +1,106 @@
+//! Stopping the server with `listen_with_shutdown`
+use expresso::prelude::*;
+use std::time::Duration;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::oneshot,
+    time::{sleep, timeout},
+};
+
+#[tokio::test]
+async fn the_signal_stops_listening_after_in_flight_requests() {
+    let app = Expresso::new();
+    app.get(
+        "/slow",
+        (|_req: Request, res: Response, _next: Next| async move {
+            sleep(Duration::from_millis(200)).await;
+            res.send("finished")
+        },),
+    )
+    .await;
+
+    let (stop, signal) = oneshot::channel::<()>();
+    let (ready, bound) = oneshot::channel();
+    let server = tokio::spawn(async move {
+        app.listen_with_shutdown(
+            "127.0.0.1:0",
+            async {
+                let _ = signal.await;
+            },
+            move |addr| {
+                let _ = ready.send(addr);
+            },
+        )
+        .await
+    });
+    let addr = bound.await.unwrap();
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"GET /slow HTTP/1.1\r\nHost: test\r\n\r\n")
+        .await
+        .unwrap();
+    sleep(Duration::from_millis(50)).await;
+    stop.send(()).unwrap();
+
+    let result = timeout(Duration::from_secs(2), server)
+        .await
+        .expect("listen did not return after the signal");
+    assert!(result.unwrap().is_ok());
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.ends_with("finished"), "{}", response);
+    assert!(response.contains("Connection: close"));
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn silent_connections_do_not_hold_up_shutdown() {
+    let app = Expresso::new();
+    app.get(
+        "/",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("served") },),
+    )
+    .await;
+
+    let (stop, signal) = oneshot::channel::<()>();
+    let (ready, bound) = oneshot::channel();
+    let server = tokio::spawn(async move {
+        app.listen_with_shutdown(
+            "127.0.0.1:0",
+            async {
+                let _ = signal.await;
+            },
+            move |addr| {
+                let _ = ready.send(addr);
+            },
+        )
+        .await
+    });
+    let addr = bound.await.unwrap();
+
+    // One client sends nothing, another has started its request
+    let mut silent = TcpStream::connect(addr).await.unwrap();
+    let mut started = TcpStream::connect(addr).await.unwrap();
+    started
+        .write_all(b"GET / HTTP/1.1\r\nHost: test\r\n")
+        .await
+        .unwrap();
+    sleep(Duration::from_millis(50)).await;
+    stop.send(()).unwrap();
+    sleep(Duration::from_millis(50)).await;
+    started.write_all(b"\r\n").await.unwrap();
+
+    let result = timeout(Duration::from_secs(2), server)
+        .await
+        .expect("listen waited on the silent connection");
+    assert!(result.unwrap().is_ok());
+
+    let mut response = String::new();
+    silent.read_to_string(&mut response).await.unwrap();
+    assert_eq!(response, "");
+    started.read_to_string(&mut response).await.unwrap();
+    assert!(response.ends_with("served"), "{}", response);
+}
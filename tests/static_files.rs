@@ -0,0 +1,83 @@
+//! Serving a directory with `static_files`
+use expresso::{middlewares::static_files, prelude::*};
+use std::net::SocketAddr;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::oneshot,
+};
+
+const PUBLIC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/public");
+
+async fn start() -> SocketAddr {
+    let app = Expresso::new();
+    app.use_middleware(static_files::serve(PUBLIC)).await;
+    app.get(
+        "/missing.txt",
+        (|_req: Request, res: Response, _next: Next| async move { res.send("from a route") },),
+    )
+    .await;
+    let (ready, bound) = oneshot::channel();
+    tokio::spawn(async move {
+        app.listen_with_addr("127.0.0.1:0", move |addr| {
+            let _ = ready.send(addr);
+        })
+        .await
+    });
+    bound.await.expect("server failed to bind")
+}
+
+async fn get(addr: SocketAddr, target: &str, extra: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: test\r\nConnection: close\r\n{}\r\n",
+        target, extra
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head.to_string(), body.to_string())
+}
+
+#[tokio::test]
+async fn serves_files_with_type_length_and_caching() {
+    let addr = start().await;
+    let (head, body) = get(addr, "/hello.txt", "").await;
+    let lines: Vec<_> = head.split("\r\n").collect();
+    assert_eq!(lines[0], "HTTP/1.1 200 OK");
+    assert!(lines.contains(&"Content-Type: text/plain; charset=utf-8"));
+    assert!(lines.contains(&"Content-Length: 16"));
+    assert!(lines.contains(&"Cache-Control: no-cache"));
+    assert_eq!(body, "hello from disk\n");
+
+    let etag = head
+        .split("\r\n")
+        .find_map(|line| line.strip_prefix("ETag: "))
+        .unwrap();
+    let (head, _) = get(addr, "/hello.txt", &format!("If-None-Match: {}\r\n", etag)).await;
+    assert!(head.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+    assert!(!head.contains("Content-Length"), "{}", head);
+
+    let (_, body) = get(addr, "/docs/", "").await;
+    assert_eq!(body, "<h1>docs</h1>\n");
+}
+
+#[tokio::test]
+async fn traversal_is_refused() {
+    let addr = start().await;
+    for target in ["/../localhost-key.pem", "/docs/%2e%2e/../localhost-key.pem"] {
+        let (head, body) = get(addr, target, "").await;
+        assert!(head.starts_with("HTTP/1.1 403 Forbidden\r\n"), "{}", target);
+        assert!(!body.contains("PRIVATE KEY"));
+    }
+}
+
+#[tokio::test]
+async fn missing_files_fall_through_to_the_next_handler() {
+    let addr = start().await;
+    let (_, body) = get(addr, "/missing.txt", "").await;
+    assert_eq!(body, "from a route");
+    let (head, _) = get(addr, "/nowhere.txt", "").await;
+    assert!(head.starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
@@ -0,0 +1,33 @@
+//! `{{name}}` templating: escaping, literal braces and missing variables
+use expresso::{http::template::render, prelude::*};
+use std::collections::HashMap;
+
+fn vars(pairs: &[(&'static str, &str)]) -> HashMap<&'static str, String> {
+    pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+}
+
+#[test]
+fn substitutes_and_escapes_values() {
+    let vars = vars(&[("name", r#"<b>"Tom" & 'Jerry'</b>"#), ("n", "3")]);
+    assert_eq!(
+        render("Hi {{name}}, {{ n }} new", &vars),
+        "Hi &lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;, 3 new"
+    );
+}
+
+#[test]
+fn triple_brace_is_a_literal_and_missing_variables_stay() {
+    let vars = vars(&[("x", "1")]);
+    assert_eq!(render("{{{x}} = {{x}}", &vars), "{{x}} = 1");
+    assert_eq!(render("{{missing}} and {{x", &vars), "{{missing}} and {{x");
+}
+
+#[test]
+fn render_sets_an_html_body() {
+    let res = Response::new().render("<p>{{v}}</p>", &vars(&[("v", "<")]));
+    assert_eq!(res.body_text(), Some("<p>&lt;</p>"));
+    assert_eq!(
+        res.header("Content-Type").map(String::as_str),
+        Some("text/html; charset=utf-8")
+    );
+}